@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use color_eyre::Result;
+use glob::Pattern;
+
+use crate::{find_files, BinswapGithub, Error, ExtractedTree};
+
+impl BinswapGithub {
+    /// Fails with [`Error::ExpectedEntryMissing`] unless one of the extracted
+    /// files matches `require_archive_entry`, if it is given.
+    pub(crate) fn check_extracted_entries(
+        &self,
+        extracted_files: &dyn ExtractedTree,
+    ) -> Result<()> {
+        let Some(required) = &self.require_archive_entry else {
+            return Ok(());
+        };
+
+        let pattern = Pattern::new(required)?;
+        let mut found = false;
+        find_files(extracted_files, Path::new("."), &mut |path| {
+            found |= entry_matches(&pattern, path)
+        });
+        if !found {
+            return Err(missing(required));
+        }
+        Ok(())
+    }
+}
+
+/// Whether the path of an entry, or its file name, matches `pattern`.
+fn entry_matches(pattern: &Pattern, path: &Path) -> bool {
+    let path = path.strip_prefix(".").unwrap_or(path);
+    let name = path.file_name().and_then(|name| name.to_str());
+    pattern.matches_path(path) || matches!(name, Some(name) if pattern.matches(name))
+}
+
+fn missing(pattern: &str) -> color_eyre::Report {
+    Error::ExpectedEntryMissing {
+        pattern: pattern.to_string(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{builder, ExtractedDir};
+
+    #[test]
+    fn extracted_entries_are_matched_by_path_or_file_name() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("tool-1.0.0/bin")).unwrap();
+        std::fs::write(dir.path().join("tool-1.0.0/bin/tool"), "").unwrap();
+        let extracted = ExtractedDir(dir.path().to_path_buf());
+        let updater = |pattern: &str| {
+            builder()
+                .repo_author("author")
+                .repo_name("repo")
+                .bin_name("tool")
+                .require_archive_entry(pattern)
+                .build()
+                .unwrap()
+        };
+
+        updater("*/bin/tool")
+            .check_extracted_entries(&extracted)
+            .unwrap();
+        updater("tool").check_extracted_entries(&extracted).unwrap();
+        let missing = updater("*/share/man/*")
+            .check_extracted_entries(&extracted)
+            .unwrap_err();
+        assert!(matches!(
+            missing.downcast_ref(),
+            Some(crate::Error::ExpectedEntryMissing { .. })
+        ));
+    }
+}
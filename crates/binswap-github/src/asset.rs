@@ -0,0 +1,209 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use binstalk::manifests::cargo_toml_binstall::{PkgFmt, PkgMeta};
+
+use color_eyre::Result;
+
+use crate::{AssetInfo, BinswapGithub, Error, ReleaseInfo};
+
+/// The file names `cargo-binstall` looks for in a GitHub release when no
+/// `pkg_url` is given, in the order they are tried.
+const DEFAULT_FILE_NAMES: &[&str] = &[
+    "{ name }-{ target }-v{ version }{ archive-suffix }",
+    "{ name }-{ target }-{ version }{ archive-suffix }",
+    "{ name }-{ version }-{ target }{ archive-suffix }",
+    "{ name }-v{ version }-{ target }{ archive-suffix }",
+    "{ name }_{ target }_v{ version }{ archive-suffix }",
+    "{ name }_{ target }_{ version }{ archive-suffix }",
+    "{ name }_{ version }_{ target }{ archive-suffix }",
+    "{ name }_v{ version }_{ target }{ archive-suffix }",
+    "{ name }-{ target }{ archive-suffix }",
+    "{ name }_{ target }{ archive-suffix }",
+];
+
+const PKG_FMTS: &[PkgFmt] = &[
+    PkgFmt::Tar,
+    PkgFmt::Tbz2,
+    PkgFmt::Tgz,
+    PkgFmt::Txz,
+    PkgFmt::Tzstd,
+    PkgFmt::Zip,
+    PkgFmt::Bin,
+];
+
+impl BinswapGithub {
+    /// Identifies the asset of `release` which is downloaded for `target`,
+    /// by matching the asset names against the same templates as
+    /// `cargo-binstall` uses.
+    pub(crate) fn matched_asset<'a>(
+        &self,
+        release: &'a ReleaseInfo,
+        version: &str,
+        target: &str,
+        meta: &PkgMeta,
+    ) -> Option<(&'a AssetInfo, PkgFmt)> {
+        let name = self.asset_name.as_deref().unwrap_or(&self.bin_name);
+        let is_windows = target.contains("windows");
+
+        let templates = match &meta.pkg_url {
+            Some(pkg_url) => vec![pkg_url.rsplit('/').next().unwrap_or(pkg_url)],
+            None => DEFAULT_FILE_NAMES.to_vec(),
+        };
+        let pkg_fmts = match &meta.pkg_fmt {
+            Some(pkg_fmt) => std::slice::from_ref(pkg_fmt),
+            None => PKG_FMTS,
+        };
+
+        for template in templates {
+            for &pkg_fmt in pkg_fmts {
+                for suffix in pkg_fmt.extensions(is_windows) {
+                    let file_name = render(template, |key| match key {
+                        "name" => Some(name),
+                        "version" => Some(version),
+                        "target" => Some(target),
+                        "archive-suffix" => Some(suffix),
+                        "archive-format" | "format" => Some(suffix.trim_start_matches('.')),
+                        "binary-ext" => Some(if is_windows { ".exe" } else { "" }),
+                        _ => None,
+                    });
+                    if let Some(asset) = release.assets.iter().find(|a| a.name == file_name) {
+                        return Some((asset, pkg_fmt));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The path of the binary `bin` in the asset for `target`, given by
+    /// rendering the `bin_dir` template.
+    pub(crate) fn bin_dir_path(
+        &self,
+        bin_dir: &str,
+        version: &str,
+        target: &str,
+        bin: &str,
+    ) -> PathBuf {
+        let name = self.asset_name.as_deref().unwrap_or(&self.bin_name);
+        let is_windows = target.contains("windows");
+        let path = render(bin_dir, |key| match key {
+            "name" => Some(name),
+            "version" => Some(version),
+            "target" => Some(target),
+            "bin" => Some(bin),
+            "binary-ext" => Some(if is_windows { ".exe" } else { "" }),
+            _ => None,
+        });
+        PathBuf::from(path.trim_start_matches("./"))
+    }
+
+    /// Whether the file name of an asset passes `asset_must_contain` and
+    /// `asset_must_not_contain`.
+    pub(crate) fn passes_asset_filters(&self, name: &str) -> bool {
+        self.asset_must_contain
+            .iter()
+            .all(|part| name.contains(part.as_str()))
+            && !self
+                .asset_must_not_contain
+                .iter()
+                .any(|part| name.contains(part.as_str()))
+    }
+
+    /// Identifies the asset of `release` for `target` which passes the
+    /// asset filters, by the name and target contained in its file name and
+    /// it having a recognized format. Fails with [`Error::AmbiguousAsset`]
+    /// if several assets pass.
+    pub(crate) fn filtered_asset<'a>(
+        &self,
+        release: &'a ReleaseInfo,
+        target: &str,
+    ) -> Result<Option<(&'a AssetInfo, PkgFmt)>> {
+        let name = self.asset_name.as_deref().unwrap_or(&self.bin_name);
+        let mut candidates = release.assets.iter().filter_map(|asset| {
+            if !asset.name.contains(name)
+                || !asset.name.contains(target)
+                || !self.passes_asset_filters(&asset.name)
+            {
+                return None;
+            }
+            let pkg_fmt = mapped_pkg_fmt(&self.extension_format_map, &asset.name)
+                .or_else(|| PkgFmt::guess_pkg_format(&asset.name))?;
+            Some((asset, pkg_fmt))
+        });
+
+        let Some(candidate) = candidates.next() else {
+            return Ok(None);
+        };
+        let rest: Vec<_> = candidates.collect();
+        if !rest.is_empty() {
+            return Err(Error::AmbiguousAsset {
+                target: target.to_string(),
+                candidates: [candidate]
+                    .into_iter()
+                    .chain(rest)
+                    .map(|(asset, _)| asset.name.clone())
+                    .collect(),
+            }
+            .into());
+        }
+        Ok(Some(candidate))
+    }
+
+    /// Identifies the asset of `release` for `target` whose extension is in
+    /// `extension_format_map`, by the name and target contained in its file
+    /// name.
+    pub(crate) fn mapped_asset<'a>(
+        &self,
+        release: &'a ReleaseInfo,
+        target: &str,
+    ) -> Option<(&'a AssetInfo, PkgFmt)> {
+        let name = self.asset_name.as_deref().unwrap_or(&self.bin_name);
+        release
+            .assets
+            .iter()
+            .filter(|asset| asset.name.contains(name) && asset.name.contains(target))
+            .find_map(|asset| {
+                let pkg_fmt = mapped_pkg_fmt(&self.extension_format_map, &asset.name)?;
+                Some((asset, pkg_fmt))
+            })
+    }
+}
+
+/// The format of the asset named `name` according to
+/// `extension_format_map`, if any of its extensions match. The longest
+/// matching extension wins, and the empty extension matches names without a
+/// recognized extension.
+pub(crate) fn mapped_pkg_fmt(map: &HashMap<String, PkgFmt>, name: &str) -> Option<PkgFmt> {
+    map.iter()
+        .filter(|(extension, _)| {
+            if extension.is_empty() {
+                PkgFmt::guess_pkg_format(name).is_none()
+            } else {
+                name.ends_with(extension.as_str())
+            }
+        })
+        .max_by_key(|(extension, _)| extension.len())
+        .map(|(_, &pkg_fmt)| pkg_fmt)
+}
+
+/// Replaces the `{ key }` placeholders in `template` with their values, or
+/// leaves them as is if `value` has none.
+fn render<'a>(template: &str, value: impl Fn(&str) -> Option<&'a str>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let placeholder = &rest[start..start + end + 1];
+        match value(placeholder[1..placeholder.len() - 1].trim()) {
+            Some(value) => rendered.push_str(value),
+            None => rendered.push_str(placeholder),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
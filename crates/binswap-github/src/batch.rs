@@ -0,0 +1,165 @@
+use std::path::{Path, PathBuf};
+
+use binstalk::helpers::remote::Client;
+use color_eyre::Result;
+
+use crate::{
+    file_name,
+    report::{can_prompt, terminal, ExecutableCommand, Print, ResetColor, Stylize},
+    verify::guess_version,
+    BinswapGithub, Resolved, SkipReason, SwapOutcome, SwapStatus,
+};
+
+/// An update whose release has been resolved, but which has not been
+/// downloaded or installed yet. It is created by
+/// [`plan_write_to`](BinswapGithub::plan_write_to), and installed either on
+/// its own or together with others by [`install_batch`].
+#[derive(Debug)]
+pub struct PlannedUpdate {
+    config: BinswapGithub,
+    client: Client,
+    resolved: Resolved,
+    target_binary: PathBuf,
+    skip_reason: Option<SkipReason>,
+    installed_version: Option<String>,
+}
+
+impl BinswapGithub {
+    /// Resolves the release which would be written to `target_binary`,
+    /// without downloading it.
+    ///
+    /// The version of the binary currently at `target_binary` is guessed by
+    /// running it with `--version`.
+    pub async fn plan_write_to(&self, target_binary: impl AsRef<Path>) -> Result<PlannedUpdate> {
+        let this = &self.fresh_budget();
+        let target_binary = target_binary.as_ref();
+        file_name(target_binary)?;
+
+        let client = this.client()?;
+        let resolved = this.resolve(&client).await?;
+        let current_version = this.current_version_of(Some(target_binary));
+        let skip_reason = this.skip_reason(&resolved, current_version.as_deref())?;
+        let installed_version = match (&skip_reason, current_version) {
+            (Some(_), _) => None,
+            (None, Some(current_version)) => Some(current_version),
+            (None, None) => guess_version(target_binary).await,
+        };
+
+        Ok(PlannedUpdate {
+            config: this.clone(),
+            client,
+            resolved,
+            target_binary: target_binary.to_path_buf(),
+            skip_reason,
+            installed_version,
+        })
+    }
+}
+
+impl PlannedUpdate {
+    /// The version which would be installed.
+    pub fn version(&self) -> &str {
+        &self.resolved.version
+    }
+    /// The version of the currently installed binary, if it could be
+    /// determined.
+    pub fn installed_version(&self) -> Option<&str> {
+        self.installed_version.as_deref()
+    }
+    /// The path the binary would be written to.
+    pub fn target_binary(&self) -> &Path {
+        &self.target_binary
+    }
+    /// Why the update would be skipped, if it would be.
+    pub fn skip_reason(&self) -> Option<&SkipReason> {
+        self.skip_reason.as_ref()
+    }
+
+    /// Downloads and writes the binary, prompting for confirmation unless
+    /// `no_confirm` is set.
+    pub async fn install(self) -> Result<SwapOutcome> {
+        self.config
+            .print_updating(file_name(&self.target_binary)?)?;
+        self.config
+            .fresh_budget()
+            .write_resolved_to(&self.client, self.resolved, &self.target_binary)
+            .await
+    }
+
+    /// Describes the update, such as `rg 13.0.0 → 14.0.0`.
+    fn summary(&self) -> String {
+        let name = self.target_binary.file_name().unwrap().to_string_lossy();
+        match &self.installed_version {
+            Some(installed) => format!("{name} {installed} → {}", self.version()),
+            None => format!("{name} → {}", self.version()),
+        }
+    }
+
+    /// The outcome of not installing the update, with `status`.
+    fn not_installed(self, status: SwapStatus) -> SwapOutcome {
+        SwapOutcome {
+            version: self.resolved.version,
+            build_id: self.resolved.build_id,
+            installed_path: self.target_binary,
+            target_used: String::new(),
+            bytes_written: 0,
+            bytes_downloaded: 0,
+            download_duration: None,
+            dry_run: self.config.dry_run,
+            status,
+            companion_paths: vec![],
+            previous_version: None,
+        }
+    }
+}
+
+/// Installs all of the `updates` after prompting for confirmation once, rather
+/// than once per update. The prompt is left out if none of the updates would
+/// prompt on their own, or they are all skipped.
+///
+/// If the prompt is declined nothing is installed, and the updates which
+/// would be skipped are reported as such. Returns the outcomes in the order
+/// of `updates`.
+pub async fn install_batch(updates: Vec<PlannedUpdate>) -> Result<Vec<SwapOutcome>> {
+    let pending: Vec<&PlannedUpdate> = updates
+        .iter()
+        .filter(|update| update.skip_reason.is_none())
+        .collect();
+
+    let confirmed = if pending.iter().all(|update| update.config.no_confirm) {
+        true
+    } else if !can_prompt() {
+        for update in &pending {
+            if !update.config.no_confirm {
+                update.config.noninteractive_answer()?;
+            }
+        }
+        true
+    } else {
+        if !pending.iter().all(|update| update.config.quiet) {
+            let summary: Vec<String> = pending.iter().map(|update| update.summary()).collect();
+            terminal()
+                .execute(Print("\n  About to update ".green()))?
+                .execute(Print(summary.join(", ")))?
+                .execute(Print("\n"))?
+                .execute(ResetColor)?;
+        }
+        crate::confirm(None).await
+    };
+
+    let mut outcomes = Vec::with_capacity(updates.len());
+    for mut update in updates {
+        if !confirmed {
+            let status = match update.skip_reason.take() {
+                Some(reason) => SwapStatus::Skipped { reason },
+                None => SwapStatus::Declined,
+            };
+            outcomes.push(update.not_installed(status));
+            continue;
+        }
+        update.config.no_confirm = true;
+        outcomes.push(update.install().await?);
+    }
+
+    Ok(outcomes)
+}
@@ -0,0 +1,86 @@
+use binstalk::{
+    helpers::remote::{Client, StatusCode},
+    manifests::cargo_toml_binstall::{Meta, PkgMeta},
+};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use serde::Deserialize;
+
+use crate::{BinswapGithub, Resolved};
+
+/// The part of a `Cargo.toml` holding the `cargo-binstall` metadata.
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    package: Option<Package>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Package {
+    metadata: Option<Meta>,
+}
+
+impl BinswapGithub {
+    /// The `[package.metadata.binstall]` section of the `Cargo.toml` at
+    /// `binstall_manifest` in the tagged commit of the resolved release, or
+    /// the default metadata if not configured.
+    pub(crate) async fn fetch_binstall_meta(
+        &self,
+        client: &Client,
+        resolved: &Resolved,
+    ) -> Result<PkgMeta> {
+        let Some(path) = &self.binstall_manifest else {
+            return Ok(PkgMeta::default());
+        };
+        self.ensure_github("reading the binstall manifest")?;
+
+        let tag = match &resolved.release {
+            Some(release) => release.tag_name.clone(),
+            None => {
+                self.fetch_release_for_version(client, &resolved.version)
+                    .await?
+                    .tag_name
+            }
+        };
+
+        let mut url = self.api_url(&format!("contents/{path}"))?;
+        url.query_pairs_mut().append_pair("ref", &tag);
+        let body = self
+            .retrying(|| async {
+                let res = self
+                    .api_send_accepting(client, &url, "application/vnd.github.raw")
+                    .await?;
+                if res.status() == StatusCode::NOT_FOUND {
+                    return Err(eyre!("there is no `{path}` at the tag `{tag}`"));
+                }
+                Ok(res.error_for_status()?.bytes().await?)
+            })
+            .await?;
+
+        let manifest: Manifest = toml::from_str(std::str::from_utf8(&body)?)
+            .wrap_err_with(|| format!("failed to parse `{path}` at the tag `{tag}`"))?;
+
+        Ok(manifest
+            .package
+            .and_then(|package| package.metadata)
+            .and_then(|metadata| metadata.binstall)
+            .unwrap_or_default())
+    }
+
+    /// The metadata for `target`, with the overrides for it applied, followed
+    /// by the options of this builder which override the metadata.
+    pub(crate) fn target_meta(&self, meta: &PkgMeta, target: &str) -> PkgMeta {
+        let mut target_meta = meta.merge_overrides(meta.overrides.get(target));
+        if let Some(pkg_fmt) = self.pkg_fmt {
+            target_meta.pkg_fmt = Some(pkg_fmt);
+        }
+        if let Some(bin_dir) = &self.bin_dir {
+            target_meta.bin_dir = Some(bin_dir.clone());
+        }
+        if let Some(pub_key) = &self.pub_key {
+            target_meta.pub_key = Some(pub_key.clone());
+        }
+        target_meta
+    }
+}
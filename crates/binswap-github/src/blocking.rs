@@ -0,0 +1,53 @@
+//! Blocking wrappers around the asynchronous methods, for applications which
+//! do not otherwise use an async runtime.
+//!
+//! Each call spins up a current-thread tokio runtime and drives the
+//! asynchronous method to completion on it. They must therefore not be called
+//! from within an existing tokio runtime, as tokio panics when a runtime is
+//! started from within another one; use the asynchronous methods there
+//! instead.
+
+use std::path::Path;
+
+use color_eyre::Result;
+use tokio::runtime::Runtime;
+
+use crate::{BinswapGithub, SwapOutcome};
+
+impl BinswapGithub {
+    /// Blocking version of
+    /// [`fetch_and_write_to`](BinswapGithub::fetch_and_write_to).
+    ///
+    /// This must not be called from within an existing tokio runtime.
+    ///
+    /// ```no_run
+    /// binswap_github::builder()
+    ///     .repo_author("BurntSushi")
+    ///     .repo_name("ripgrep")
+    ///     .asset_name("ripgrep")
+    ///     .bin_name("rg")
+    ///     .build()?
+    ///     .fetch_and_write_to_blocking("./rg")?;
+    /// # Ok::<(), color_eyre::Report>(())
+    /// ```
+    pub fn fetch_and_write_to_blocking(
+        &self,
+        target_binary: impl AsRef<Path>,
+    ) -> Result<SwapOutcome> {
+        runtime()?.block_on(self.fetch_and_write_to(target_binary))
+    }
+
+    /// Blocking version of
+    /// [`fetch_and_write_in_place_of_current_exec`](BinswapGithub::fetch_and_write_in_place_of_current_exec).
+    ///
+    /// This must not be called from within an existing tokio runtime.
+    pub fn fetch_and_write_in_place_of_current_exec_blocking(&self) -> Result<SwapOutcome> {
+        runtime()?.block_on(self.fetch_and_write_in_place_of_current_exec())
+    }
+}
+
+fn runtime() -> Result<Runtime> {
+    Ok(tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?)
+}
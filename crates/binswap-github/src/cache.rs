@@ -0,0 +1,142 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use binstalk::helpers::remote::Client;
+use color_eyre::Result;
+use sha2::{Digest, Sha256};
+
+use crate::{BinswapGithub, ForgeKind, ReleaseInfo};
+
+/// A cache of the latest releases of repositories, which can be shared
+/// between builders, such that the latest release of a repository is not
+/// looked up again until `ttl` has passed.
+///
+/// ```no_run
+/// # use std::{sync::Arc, time::Duration};
+/// # use binswap_github::VersionCache;
+/// let cache = Arc::new(VersionCache::new(Duration::from_secs(10 * 60)));
+/// let updater = binswap_github::builder()
+///     .repo_author("BurntSushi")
+///     .repo_name("ripgrep")
+///     .bin_name("rg")
+///     .version_cache(cache.clone())
+///     .build()?;
+/// # Ok::<(), color_eyre::Report>(())
+/// ```
+#[derive(Debug)]
+pub struct VersionCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<CacheKey, (Instant, ReleaseInfo)>>,
+}
+
+/// Identifies a lookup of the latest release of a repository. Whether
+/// pre-releases and drafts are allowed is part of it, as it changes which
+/// release is the latest, and so is the digest of the token, as it changes
+/// which releases are visible.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    forge: ForgeKind,
+    api_base_url: String,
+    author: String,
+    name: String,
+    allow_prerelease: bool,
+    allow_draft: bool,
+    token_digest: Option<[u8; 32]>,
+}
+
+impl VersionCache {
+    /// Creates an empty cache, whose entries are used for `ttl` after they
+    /// were looked up.
+    pub fn new(ttl: Duration) -> VersionCache {
+        VersionCache {
+            ttl,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Removes all entries, such that the latest releases are looked up again.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<ReleaseInfo> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((looked_up, release)) if looked_up.elapsed() < self.ttl => Some(release.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: CacheKey, release: ReleaseInfo) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key, (Instant::now(), release));
+    }
+}
+
+impl BinswapGithub {
+    /// Fetches the latest release, from the `version_cache` if it holds it.
+    pub(crate) async fn fetch_latest_release(&self, client: &Client) -> Result<ReleaseInfo> {
+        let Some(cache) = &self.version_cache else {
+            return self.fetch_latest_release_uncached(client).await;
+        };
+
+        let key = self.cache_key();
+        if let Some(release) = cache.get(&key) {
+            return Ok(release);
+        }
+        let release = self.fetch_latest_release_uncached(client).await?;
+        cache.insert(key, release.clone());
+        Ok(release)
+    }
+
+    /// The key of the lookup of the latest release of the repository.
+    fn cache_key(&self) -> CacheKey {
+        CacheKey {
+            forge: self.forge,
+            api_base_url: self.api_base_url().to_string(),
+            author: self.repo_author.clone(),
+            name: self.repo_name.clone(),
+            allow_prerelease: self.allow_prerelease,
+            allow_draft: self.allow_draft,
+            token_digest: self
+                .token()
+                .map(|token| Sha256::digest(token.as_bytes()).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_depends_on_the_forge_and_token() {
+        let key = |forge, token: Option<&str>| {
+            let mut builder = crate::builder();
+            builder
+                .repo_author("author")
+                .repo_name("repo")
+                .bin_name("tool")
+                .forge(forge)
+                .api_base_url("https://forge.example.com/api");
+            if let Some(token) = token {
+                builder.github_token(token);
+            }
+            builder.build().unwrap().cache_key()
+        };
+
+        let gitea = key(ForgeKind::Gitea, Some("a"));
+        assert_eq!(gitea, key(ForgeKind::Gitea, Some("a")));
+        assert_ne!(gitea, key(ForgeKind::Gitea, Some("b")));
+        assert_ne!(gitea, key(ForgeKind::GitLab, Some("a")));
+    }
+}
@@ -0,0 +1,344 @@
+use std::{fmt, io, path::Path, process::Output};
+
+use color_eyre::{eyre::eyre, Result};
+use tokio::process::Command;
+
+use crate::{
+    report::{ExecutableCommand, Print, Stylize},
+    BinswapGithub, Error, SwapEvent,
+};
+
+/// The arguments to run the downloaded binary with to check that it is
+/// executable. A string is split on whitespace, such that
+/// `"config check --strict"` is three arguments, while a list of arguments
+/// is passed as is.
+///
+/// ```
+/// # use binswap_github::CheckArgs;
+/// assert_eq!(
+///     CheckArgs::from("config check --strict"),
+///     CheckArgs::from(vec!["config", "check", "--strict"]),
+/// );
+/// let updater = binswap_github::builder()
+///     .repo_author("BurntSushi")
+///     .repo_name("ripgrep")
+///     .bin_name("rg")
+///     .check_with_cmd(vec!["config".to_string(), "--path=a b".to_string()])
+///     .build()?;
+/// # Ok::<(), color_eyre::Report>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckArgs(Vec<String>);
+
+impl From<&str> for CheckArgs {
+    fn from(args: &str) -> Self {
+        CheckArgs(args.split_whitespace().map(String::from).collect())
+    }
+}
+
+impl From<String> for CheckArgs {
+    fn from(args: String) -> Self {
+        args.as_str().into()
+    }
+}
+
+impl From<Vec<String>> for CheckArgs {
+    fn from(args: Vec<String>) -> Self {
+        CheckArgs(args)
+    }
+}
+
+impl From<Vec<&str>> for CheckArgs {
+    fn from(args: Vec<&str>) -> Self {
+        CheckArgs(args.into_iter().map(String::from).collect())
+    }
+}
+
+impl fmt::Display for CheckArgs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.join(" "))
+    }
+}
+
+/// What the check command has to produce for the check to pass, rather than
+/// exiting successfully, see
+/// [`check_expect`](crate::BinswapGithubBuilder::check_expect).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CheckExpectation {
+    /// The check command exits with this code, which may be non-zero for
+    /// binaries which do so on `--help`.
+    ExitCode(i32),
+    /// The stdout of the check command contains this string, such as the
+    /// name of the tool and its version.
+    StdoutContains(String),
+    /// The stderr of the check command contains this string.
+    StderrContains(String),
+}
+
+impl CheckExpectation {
+    /// Whether `output` meets the expectation.
+    fn is_met_by(&self, output: &Output) -> bool {
+        match self {
+            CheckExpectation::ExitCode(code) => output.status.code() == Some(*code),
+            CheckExpectation::StdoutContains(needle) => {
+                String::from_utf8_lossy(&output.stdout).contains(needle.as_str())
+            }
+            CheckExpectation::StderrContains(needle) => {
+                String::from_utf8_lossy(&output.stderr).contains(needle.as_str())
+            }
+        }
+    }
+}
+
+impl fmt::Display for CheckExpectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckExpectation::ExitCode(code) => write!(f, "exit with code {code}"),
+            CheckExpectation::StdoutContains(needle) => write!(f, "print `{needle}` to stdout"),
+            CheckExpectation::StderrContains(needle) => write!(f, "print `{needle}` to stderr"),
+        }
+    }
+}
+
+/// A sandbox to run the check command in, such that the downloaded binary
+/// cannot touch the filesystem or network while being verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SandboxSpec {
+    /// Use the first of the available sandboxes below.
+    Auto,
+    /// Use [`bwrap`](https://github.com/containers/bubblewrap), with a
+    /// read-only view of the filesystem and no network.
+    Bubblewrap,
+    /// Use [`firejail`](https://github.com/netblue30/firejail), with a
+    /// read-only view of the filesystem and no network.
+    Firejail,
+    /// Use a custom command. Each occurrence of `{bin}` is replaced by the
+    /// path of the downloaded binary, and the check command is appended.
+    Template(Vec<String>),
+}
+
+impl SandboxSpec {
+    /// The possible command lines to run `bin` in, in order of preference.
+    fn command_lines(&self, bin: &Path) -> Vec<Vec<String>> {
+        let bin_str = bin.display().to_string();
+        let bin_dir = bin.parent().unwrap_or(bin).display().to_string();
+
+        let bubblewrap = || {
+            [
+                "bwrap",
+                "--ro-bind",
+                "/",
+                "/",
+                "--dev",
+                "/dev",
+                "--proc",
+                "/proc",
+                "--tmpfs",
+                "/tmp",
+                "--ro-bind",
+                &bin_dir,
+                &bin_dir,
+                "--unshare-all",
+                "--die-with-parent",
+                "--",
+                &bin_str,
+            ]
+            .map(String::from)
+            .to_vec()
+        };
+        let firejail = || {
+            [
+                "firejail",
+                "--quiet",
+                "--noprofile",
+                "--net=none",
+                "--read-only=/",
+                "--",
+                &bin_str,
+            ]
+            .map(String::from)
+            .to_vec()
+        };
+
+        match self {
+            SandboxSpec::Auto => vec![bubblewrap(), firejail()],
+            SandboxSpec::Bubblewrap => vec![bubblewrap()],
+            SandboxSpec::Firejail => vec![firejail()],
+            SandboxSpec::Template(template) => vec![template
+                .iter()
+                .map(|arg| arg.replace("{bin}", &bin_str))
+                .collect()],
+        }
+    }
+}
+
+impl BinswapGithub {
+    /// Runs the check command on the downloaded binary, unless disabled.
+    pub(crate) async fn check(&self, bin_path: &Path) -> Result<()> {
+        if self.no_check_with_cmd {
+            return Ok(());
+        }
+
+        self.emit(SwapEvent::Checking);
+
+        let res = if let Some(sandbox) = &self.check_sandbox {
+            match self.run_sandboxed(sandbox, bin_path).await? {
+                Some(res) => res,
+                None if self.check_sandbox_strict => {
+                    return Err(Error::SandboxUnavailable {
+                        sandbox: sandbox.clone(),
+                    }
+                    .into())
+                }
+                None => {
+                    self.stderr().execute(Print(
+                        " > No sandbox available, skipping check of downloaded binary\n"
+                            .yellow()
+                            .italic(),
+                    ))?;
+                    return Ok(());
+                }
+            }
+        } else {
+            let mut command = Command::new(bin_path);
+            command.args(&self.check_with_cmd.0);
+            self.check_output(command).await??
+        };
+        match &self.check_expect {
+            Some(expectation) if !expectation.is_met_by(&res) => {
+                return Err(eyre!(
+                    "`{}` on downloaded binary did not {expectation}, it exited with {}",
+                    self.check_with_cmd,
+                    res.status,
+                ));
+            }
+            Some(_) => {}
+            None if !res.status.success() => {
+                return Err(eyre!(
+                    "Could not execute `{}` on downloaded binary: {}",
+                    self.check_with_cmd,
+                    res.status,
+                ));
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    /// Runs the smoke test on the installed binary, if enabled, in the same
+    /// environment and with the same timeout as the check command. The
+    /// binary was taken from the asset for `target`.
+    pub(crate) async fn smoke_test(&self, bin_path: &Path, target: &str) -> Result<()> {
+        if !self.post_install_smoke_test {
+            return Ok(());
+        }
+
+        let mut command = Command::new(bin_path);
+        command.args(&self.smoke_test_args);
+        let reason = match self.check_output(command).await {
+            Ok(Ok(res)) if res.status.success() => return Ok(()),
+            Ok(Ok(res)) => res.status.to_string(),
+            Ok(Err(e)) => e.to_string(),
+            Err(e) => e.to_string(),
+        };
+        Err(Error::SmokeTestFailed {
+            target: target.to_string(),
+            reason,
+        }
+        .into())
+    }
+
+    /// Checks that the size of the downloaded binary is within the expected
+    /// range.
+    pub(crate) async fn check_size(&self, bin_path: &Path) -> Result<()> {
+        let (min, max) = self.expected_size_range;
+        if min.is_none() && max.is_none() {
+            return Ok(());
+        }
+
+        let size = tokio::fs::metadata(bin_path).await?.len();
+        if matches!(min, Some(min) if size < min) || matches!(max, Some(max) if size > max) {
+            return Err(Error::UnexpectedBinarySize { size, min, max }.into());
+        }
+
+        Ok(())
+    }
+
+    /// Runs the check command inside of the first available sandbox, or
+    /// returns `None` if none of them are available.
+    async fn run_sandboxed(
+        &self,
+        sandbox: &SandboxSpec,
+        bin_path: &Path,
+    ) -> Result<Option<Output>> {
+        for command_line in sandbox.command_lines(bin_path) {
+            let Some((program, args)) = command_line.split_first() else {
+                continue;
+            };
+            let mut command = Command::new(program);
+            command.args(args).args(&self.check_with_cmd.0);
+            match self.check_output(command).await? {
+                Ok(output) => return Ok(Some(output)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Runs the check or smoke test `command` with the `check_env`, failing
+    /// with [`Error::CheckTimedOut`] and killing it if it takes longer than
+    /// `check_timeout`.
+    async fn check_output(&self, mut command: Command) -> Result<io::Result<Output>> {
+        if self.check_clear_env {
+            command.env_clear();
+        }
+        command
+            .envs(self.check_env.iter().map(|(key, value)| (key, value)))
+            .kill_on_drop(true);
+        tokio::time::timeout(self.check_timeout, command.output())
+            .await
+            .map_err(|_| {
+                Error::CheckTimedOut {
+                    timeout: self.check_timeout,
+                }
+                .into()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn check_passes_each_argument() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let bin_path = dir.path().join("tool");
+        std::fs::write(&bin_path, "#!/bin/sh\nprintf '[%s]' \"$@\"\n").unwrap();
+        std::fs::set_permissions(&bin_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let updater = crate::builder()
+            .repo_author("author")
+            .repo_name("repo")
+            .bin_name("tool")
+            .check_with_cmd(vec!["a b", "c"])
+            .check_expect(CheckExpectation::StdoutContains("[a b][c]".to_string()))
+            .quiet(true)
+            .build()
+            .unwrap();
+        updater.check(&bin_path).await.unwrap();
+
+        let mut command = Command::new(&bin_path);
+        command.args(&CheckArgs::from("a b c").0);
+        let output = updater.check_output(command).await.unwrap().unwrap();
+        assert_eq!(output.stdout, b"[a][b][c]");
+    }
+}
@@ -0,0 +1,100 @@
+use std::{fs::File, io, path::Path};
+
+use binstalk::helpers::remote::Client;
+use color_eyre::{eyre::eyre, Result};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    sidecar::{find_sidecar, CHECKSUM_FILES},
+    AssetInfo, BinswapGithub, Error, ReleaseInfo,
+};
+
+/// Where to find the SHA-256 checksum of the downloaded asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumKind {
+    /// A file listing the checksums of every asset of the release, keyed by
+    /// file name, such as `SHA256SUMS` or `checksums.txt`.
+    Sha256Sums,
+    /// A file next to the asset with the same name and an added `.sha256`
+    /// or `.sha256sum` extension.
+    Sidecar,
+}
+
+impl BinswapGithub {
+    /// Verifies the downloaded `asset`, which is at `path`, against its
+    /// checksum in the release, if `verify_checksum` is set, failing with
+    /// [`Error::ChecksumMismatch`] if they differ. The file is streamed
+    /// through the hash rather than read into memory.
+    pub(crate) async fn verify_checksum(
+        &self,
+        client: &Client,
+        release: &ReleaseInfo,
+        asset: &AssetInfo,
+        path: &Path,
+    ) -> Result<()> {
+        let Some(kind) = self.verify_checksum else {
+            return Ok(());
+        };
+
+        let checksum_asset = match kind {
+            ChecksumKind::Sha256Sums => release.assets.iter().find(|a| {
+                let name = a.name.to_lowercase();
+                CHECKSUM_FILES.contains(&name.as_str()) && !name.starts_with("sha512")
+            }),
+            ChecksumKind::Sidecar => find_sidecar(release, asset, &[".sha256", ".sha256sum"]),
+        };
+        let Some(checksum_file) = self.fetch_sidecar(client, checksum_asset).await? else {
+            return Err(eyre!(
+                "the release `{}` has no checksum file for `{}`",
+                release.tag_name,
+                asset.name
+            ));
+        };
+
+        let contents = String::from_utf8_lossy(&checksum_file.bytes);
+        let expected = match kind {
+            ChecksumKind::Sha256Sums => find_checksum(&contents, &asset.name),
+            ChecksumKind::Sidecar => contents.split_whitespace().next(),
+        }
+        .ok_or_else(|| {
+            eyre!(
+                "`{}` does not contain a checksum for `{}`",
+                checksum_file.name,
+                asset.name
+            )
+        })?
+        .to_lowercase();
+
+        let path = path.to_path_buf();
+        let actual = tokio::task::spawn_blocking(move || -> io::Result<String> {
+            let mut hasher = Sha256::new();
+            io::copy(&mut File::open(path)?, &mut hasher)?;
+            Ok(hex(&hasher.finalize()))
+        })
+        .await??;
+        if actual != expected {
+            return Err(Error::ChecksumMismatch {
+                asset: asset.name.clone(),
+                expected,
+                actual,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// The checksum of `name` in a file listing checksums, with lines like
+/// `<checksum>  <name>`, or `<checksum> *<name>` for binary mode.
+fn find_checksum<'a>(contents: &'a str, name: &str) -> Option<&'a str> {
+    contents.lines().find_map(|line| {
+        let (checksum, file) = line.trim().split_once(char::is_whitespace)?;
+        let file = file.trim_start().trim_start_matches('*');
+        let file = file.strip_prefix("./").unwrap_or(file);
+        (file == name).then_some(checksum)
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+
+use binstalk::helpers::{
+    download::PkgFmt,
+    remote::{Client, Url},
+};
+use color_eyre::{eyre::Context, Result};
+
+use crate::{
+    asset::mapped_pkg_fmt,
+    create_dir_all, find_files, local,
+    report::{ExecutableCommand, Print, Stylize},
+    BinswapGithub, ExtractedDir, ReleaseInfo,
+};
+
+/// An asset of the release installed alongside the binary, such as shell
+/// completions or man pages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompanionAsset {
+    /// A glob matched against the file names of the assets of the release.
+    pub pattern: String,
+    /// The directory the matching assets are written to. Archives are
+    /// extracted into it, other assets are written to it as is.
+    pub dest: PathBuf,
+}
+
+impl CompanionAsset {
+    /// Installs the assets matching `pattern` into `dest`.
+    pub fn new(pattern: impl Into<String>, dest: impl Into<PathBuf>) -> Self {
+        CompanionAsset {
+            pattern: pattern.into(),
+            dest: dest.into(),
+        }
+    }
+}
+
+impl BinswapGithub {
+    /// Downloads the companion assets of `release` into their destinations,
+    /// returning the paths of the installed files.
+    pub(crate) async fn install_companions(
+        &self,
+        client: &Client,
+        release: &ReleaseInfo,
+    ) -> Result<Vec<PathBuf>> {
+        let mut installed = vec![];
+
+        for companion in &self.companion_assets {
+            let pattern = glob::Pattern::new(&companion.pattern)
+                .wrap_err_with(|| format!("invalid asset pattern `{}`", companion.pattern))?;
+
+            for asset in release
+                .assets
+                .iter()
+                .filter(|asset| pattern.matches(&asset.name))
+            {
+                self.stderr()
+                    .execute(Print("Downloading ".magenta().italic()))?
+                    .execute(Print(&asset.name))?
+                    .execute(Print(" into ".magenta().italic()))?
+                    .execute(Print(format!("`{}`\n", companion.dest.display())))?;
+
+                create_dir_all(&companion.dest).await?;
+
+                let url = Url::parse(&asset.browser_download_url)?;
+                let pkg_fmt = mapped_pkg_fmt(&self.extension_format_map, &asset.name)
+                    .or_else(|| PkgFmt::guess_pkg_format(&asset.name))
+                    .unwrap_or(PkgFmt::Bin);
+                // The asset is extracted into the scratch space first, to
+                // tell which files it holds.
+                let download = self.scratch_dir()?;
+                let file = download.path().join(&asset.name);
+                self.retrying(|| self.download_to(client, &url, &file, |_| ()))
+                    .await
+                    .wrap_err_with(|| format!("failed to download `{}`", asset.name))?;
+                let unpacked = download.path().join("unpacked");
+                let (dir, name) = (unpacked.clone(), asset.name.clone());
+                tokio::task::spawn_blocking(move || local::extract(&file, pkg_fmt, &dir, &name))
+                    .await?
+                    .wrap_err_with(|| format!("failed to install `{}`", asset.name))?;
+
+                let mut files = vec![];
+                find_files(
+                    &ExtractedDir(unpacked.clone()),
+                    Path::new("."),
+                    &mut |file| files.push(file.to_path_buf()),
+                );
+                for file in files {
+                    let dest = companion.dest.join(&file);
+                    if let Some(parent) = dest.parent() {
+                        create_dir_all(parent).await?;
+                    }
+                    tokio::fs::copy(unpacked.join(&file), &dest).await?;
+                    installed.push(dest);
+                }
+            }
+        }
+
+        Ok(installed)
+    }
+}
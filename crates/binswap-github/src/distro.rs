@@ -0,0 +1,346 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use binstalk::helpers::remote::{Client, Url};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+
+use crate::{
+    report::{ExecutableCommand, Print, Stylize},
+    AssetInfo, BinswapGithub, Error, Fetched, ProgressEvent, Resolved, SwapEvent,
+};
+
+/// The directories of a package in which binaries are looked for.
+const BIN_DIRS: &[&str] = &["usr/bin", "bin", "usr/local/bin", "usr/sbin", "sbin"];
+
+/// A Linux distribution package to take the binary from, rather than an
+/// archive in the layout used by `cargo-binstall`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DistroPackage {
+    /// A Debian package, whose `data.tar` is extracted.
+    Deb,
+    /// An RPM package, whose `cpio` payload is extracted.
+    Rpm,
+}
+
+impl DistroPackage {
+    fn extension(self) -> &'static str {
+        match self {
+            DistroPackage::Deb => ".deb",
+            DistroPackage::Rpm => ".rpm",
+        }
+    }
+
+    /// The name of the architecture of `target` used in package names.
+    fn arch(self, target: &str) -> Option<&'static str> {
+        if !target.contains("linux") {
+            return None;
+        }
+        let (arch, _) = target.split_once('-')?;
+        let is_hard_float = target.ends_with("hf");
+        Some(match (self, arch) {
+            (DistroPackage::Deb, "x86_64") => "amd64",
+            (DistroPackage::Deb, "aarch64") => "arm64",
+            (DistroPackage::Deb, "i686" | "i586") => "i386",
+            (DistroPackage::Deb, "powerpc64le") => "ppc64el",
+            (DistroPackage::Deb, "riscv64gc") => "riscv64",
+            (DistroPackage::Deb, arch) if arch.starts_with("arm") && is_hard_float => "armhf",
+            (DistroPackage::Deb, arch) if arch.starts_with("arm") => "armel",
+            (DistroPackage::Rpm, "riscv64gc") => "riscv64",
+            (DistroPackage::Rpm, "powerpc64le") => "ppc64le",
+            (DistroPackage::Rpm, "armv7") if is_hard_float => "armv7hl",
+            (_, "x86_64") => "x86_64",
+            (_, "aarch64") => "aarch64",
+            (_, "i686") => "i686",
+            (_, "s390x") => "s390x",
+            _ => return None,
+        })
+    }
+
+    /// Whether `asset` is a package named `name` for `target`.
+    fn matches(self, asset: &AssetInfo, name: &str, target: &str) -> bool {
+        let Some(arch) = self.arch(target) else {
+            return false;
+        };
+        let Some(stem) = asset.name.strip_suffix(self.extension()) else {
+            return false;
+        };
+        stem.contains(name) && stem.split(['_', '-', '.']).any(|part| part == arch)
+    }
+}
+
+impl BinswapGithub {
+    /// Looks through the candidate targets for a package asset of the
+    /// resolved release, and extracts `bin_name` from it into `temp`.
+    pub(crate) async fn fetch_distro_package(
+        &self,
+        client: &Client,
+        resolved: Resolved,
+        package: DistroPackage,
+        temp: &Path,
+    ) -> Result<Fetched<PathBuf>> {
+        let release = match resolved.release {
+            Some(release) => release,
+            None => {
+                self.fetch_release_for_version(client, &resolved.version)
+                    .await?
+            }
+        };
+        release.ensure_has_assets()?;
+        let (targets, total) = self.candidate_targets().await;
+        let tried = targets.len();
+        let name = self.asset_name.as_deref().unwrap_or(&self.bin_name);
+
+        let (mut download_bytes, mut download_duration) = (0, None);
+        for target in targets {
+            self.emit(SwapEvent::ProbingTarget(target.clone()));
+            self.stderr()
+                .execute(Print("Looking for package for target ".magenta().italic()))?
+                .execute(Print(&target))?
+                .execute(Print("...\n".magenta().italic()))?;
+
+            let asset = release.assets.iter().find(|asset| {
+                package.matches(asset, name, &target) && self.passes_asset_filters(&asset.name)
+            });
+            let Some(asset) = asset else {
+                continue;
+            };
+
+            self.emit(SwapEvent::Downloading {
+                target: target.clone(),
+            });
+            self.stderr().execute(Print(
+                "Found a package! Downloading...\n".magenta().italic(),
+            ))?;
+
+            let url = Url::parse(&asset.browser_download_url)?;
+            let package_path = temp.join(&asset.name);
+            self.progress(ProgressEvent::Downloading {
+                downloaded: 0,
+                total: Some(asset.size),
+            });
+            let started = Instant::now();
+            self.retrying(|| {
+                self.download_with_progress(client, &url, &package_path, Some(asset.size))
+            })
+            .await?;
+            let elapsed = started.elapsed();
+            self.record_download(elapsed, asset.size);
+            download_bytes += asset.size;
+            *download_duration.get_or_insert(Duration::ZERO) += elapsed;
+
+            self.progress(ProgressEvent::Extracting);
+
+            let contents = temp.join("package");
+            let (package_path, dir) = (package_path.clone(), contents.clone());
+            tokio::task::spawn_blocking(move || match package {
+                DistroPackage::Deb => extract_deb(&package_path, &dir),
+                DistroPackage::Rpm => extract_rpm(&package_path, &dir),
+            })
+            .await?
+            .wrap_err_with(|| format!("failed to extract `{}`", asset.name))?;
+
+            let bin_name = if target.contains("windows") {
+                format!("{}.exe", self.bin_name)
+            } else {
+                self.bin_name.clone()
+            };
+            let found = BIN_DIRS
+                .iter()
+                .map(|dir| Path::new("package").join(dir).join(&bin_name))
+                .find(|path| temp.join(path).is_file());
+            if let Some(found) = found {
+                return Ok(Fetched {
+                    version: resolved.version,
+                    build_id: resolved.build_id,
+                    target,
+                    found,
+                    download_bytes,
+                    download_duration,
+                });
+            }
+
+            self.stderr().execute(Print(
+                " > No binary found in package, trying next target...\n"
+                    .red()
+                    .italic(),
+            ))?;
+        }
+
+        Err(Error::AssetNotFound {
+            version: resolved.version,
+            tried,
+            total,
+        }
+        .into())
+    }
+}
+
+/// Extracts the files of the `data.tar` member of the `ar` archive at `path`
+/// into `dir`.
+fn extract_deb(path: &Path, dir: &Path) -> Result<()> {
+    let mut file = BufReader::new(File::open(path)?);
+
+    let mut magic = [0; 8];
+    file.read_exact(&mut magic)?;
+    if &magic != b"!<arch>\n" {
+        return Err(eyre!("not a Debian package"));
+    }
+
+    loop {
+        let mut header = [0; 60];
+        match file.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                return Err(eyre!("the package has no `data.tar`"))
+            }
+            Err(e) => return Err(e.into()),
+        }
+        let name = String::from_utf8_lossy(&header[..16]);
+        let size: u64 = String::from_utf8_lossy(&header[48..58])
+            .trim()
+            .parse()
+            .wrap_err("invalid member size in the package")?;
+
+        if name
+            .trim_end()
+            .trim_end_matches('/')
+            .starts_with("data.tar")
+        {
+            let data = decompressed(file.take(size))?;
+            tar::Archive::new(data).unpack(dir)?;
+            return Ok(());
+        }
+
+        // Members are padded to an even size.
+        io::copy(&mut (&mut file).take(size + size % 2), &mut io::sink())?;
+    }
+}
+
+/// Extracts the regular files of the `cpio` payload of the RPM package at
+/// `path` into `dir`.
+fn extract_rpm(path: &Path, dir: &Path) -> Result<()> {
+    let mut file = BufReader::new(File::open(path)?);
+
+    let mut lead = [0; 96];
+    file.read_exact(&mut lead)?;
+    if lead[..4] != [0xed, 0xab, 0xee, 0xdb] {
+        return Err(eyre!("not an RPM package"));
+    }
+
+    // The signature header is padded to a multiple of 8 bytes, the header is
+    // not.
+    let signature_size = skip_rpm_header(&mut file)?;
+    io::copy(
+        &mut (&mut file).take((8 - signature_size % 8) % 8),
+        &mut io::sink(),
+    )?;
+    skip_rpm_header(&mut file)?;
+
+    let mut payload = decompressed(file)?;
+    let read_hex =
+        |bytes: &[u8]| -> Result<u64> { Ok(u64::from_str_radix(std::str::from_utf8(bytes)?, 16)?) };
+    loop {
+        let mut header = [0; 110];
+        payload.read_exact(&mut header)?;
+        if &header[..6] != b"070701" && &header[..6] != b"070702" {
+            return Err(eyre!("unsupported cpio format in the package"));
+        }
+        let mode = read_hex(&header[14..22])?;
+        let file_size = read_hex(&header[54..62])?;
+        let name_size = read_hex(&header[94..102])?;
+
+        let mut name = vec![0; name_size as usize];
+        payload.read_exact(&mut name)?;
+        let name = String::from_utf8_lossy(&name);
+        let name = name.trim_end_matches('\0');
+        // The header and name are padded to a multiple of 4 bytes, and so is
+        // the data.
+        io::copy(
+            &mut (&mut payload).take((4 - (110 + name_size) % 4) % 4),
+            &mut io::sink(),
+        )?;
+
+        if name == "TRAILER!!!" {
+            return Ok(());
+        }
+
+        let mut data = (&mut payload).take(file_size);
+        let is_regular_file = mode & 0o170000 == 0o100000;
+        match relative_path(name) {
+            Some(relative) if is_regular_file => {
+                let destination = dir.join(relative);
+                if let Some(parent) = destination.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                io::copy(&mut data, &mut File::create(&destination)?)?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(
+                        &destination,
+                        fs::Permissions::from_mode(mode as u32 & 0o7777),
+                    )?;
+                }
+            }
+            _ => {
+                io::copy(&mut data, &mut io::sink())?;
+            }
+        }
+        io::copy(
+            &mut (&mut payload).take((4 - file_size % 4) % 4),
+            &mut io::sink(),
+        )?;
+    }
+}
+
+/// Skips a header structure of an RPM package, returning its size.
+fn skip_rpm_header(file: &mut impl Read) -> Result<u64> {
+    let mut header = [0; 16];
+    file.read_exact(&mut header)?;
+    if header[..4] != [0x8e, 0xad, 0xe8, 0x01] {
+        return Err(eyre!("invalid header in the RPM package"));
+    }
+    let index_count = u32::from_be_bytes(header[8..12].try_into().unwrap());
+    let store_size = u32::from_be_bytes(header[12..16].try_into().unwrap());
+    let size = 16 * u64::from(index_count) + u64::from(store_size);
+    io::copy(&mut file.take(size), &mut io::sink())?;
+    Ok(16 + size)
+}
+
+/// Wraps `reader` in a decompressor, based on the magic bytes of its
+/// contents.
+fn decompressed<'a>(reader: impl Read + 'a) -> Result<Box<dyn Read + 'a>> {
+    let mut reader = BufReader::new(reader);
+    let magic = reader.fill_buf()?;
+    Ok(if magic.starts_with(&[0x1f, 0x8b]) {
+        Box::new(flate2::read::GzDecoder::new(reader))
+    } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        Box::new(xz2::read::XzDecoder::new(reader))
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Box::new(zstd::stream::read::Decoder::with_buffer(reader)?)
+    } else {
+        Box::new(reader)
+    })
+}
+
+/// The path of a file in a package relative to its root, unless it would
+/// escape the root.
+fn relative_path(name: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir | Component::RootDir => {}
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!path.as_os_str().is_empty()).then_some(path)
+}
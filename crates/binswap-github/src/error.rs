@@ -0,0 +1,276 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::SandboxSpec;
+
+/// Errors which callers might want to handle specifically.
+///
+/// These are returned wrapped in a [`Report`](color_eyre::eyre::Report), and
+/// can be recovered from it with
+/// [`downcast_ref`](color_eyre::eyre::Report::downcast_ref).
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The binary could not be written to `path` due to insufficient
+    /// permissions.
+    #[error(
+        "insufficient permissions to write to `{}`, try re-running {ELEVATION_HINT}",
+        path.display()
+    )]
+    ElevationRequired {
+        /// The path which could not be written to.
+        path: PathBuf,
+    },
+    /// The sandbox requested for the check command is not available.
+    #[error("the sandbox {sandbox:?} for the check command is not available")]
+    SandboxUnavailable {
+        /// The requested sandbox.
+        sandbox: SandboxSpec,
+    },
+    /// The size of the downloaded binary is outside of the expected range.
+    #[error(
+        "the downloaded binary is {size} bytes, expected {}",
+        size_range(*min, *max)
+    )]
+    UnexpectedBinarySize {
+        /// The size of the downloaded binary in bytes.
+        size: u64,
+        /// The minimum expected size in bytes.
+        min: Option<u64>,
+        /// The maximum expected size in bytes.
+        max: Option<u64>,
+    },
+    /// The GitHub API refused the request since the rate limit was exceeded.
+    #[error("the GitHub API rate limit was exceeded, try again later or provide a token")]
+    RateLimited {
+        /// When the rate limit resets, as seconds since the Unix epoch.
+        reset: Option<u64>,
+    },
+    /// The GitHub API refused the request since the token lacks a
+    /// permission.
+    #[error("the GitHub token lacks the `{permission}` permission: {message}")]
+    InsufficientTokenScope {
+        /// The permission required for the request, such as `contents=read`.
+        permission: String,
+        /// The message given by GitHub.
+        message: String,
+    },
+    /// None of the assets of the release contain the binary for any of the
+    /// candidate targets.
+    #[error(
+        "no asset of version `{version}` contains the binary for any of the targets{}",
+        targets_tried(*tried, *total)
+    )]
+    AssetNotFound {
+        /// The version of the release.
+        version: String,
+        /// How many of the candidate targets were tried, which is less than
+        /// `total` if `max_targets_to_try` was reached.
+        tried: usize,
+        /// The number of candidate targets.
+        total: usize,
+    },
+    /// The release has no files attached at all, so the project likely
+    /// distributes its binaries elsewhere.
+    #[error("the release `{tag}` has no assets")]
+    ReleaseHasNoAssets {
+        /// The tag of the release.
+        tag: String,
+    },
+    /// The media type of the release asset is not the expected one.
+    #[error("the asset `{asset}` has the content type `{content_type}`, expected `{expected}`")]
+    UnexpectedAssetContentType {
+        /// The file name of the asset.
+        asset: String,
+        /// The content type of the asset.
+        content_type: String,
+        /// The expected content type.
+        expected: String,
+    },
+    /// Several assets of the release pass the asset filters for the target.
+    #[error("several assets match the filters for `{target}`: {}", candidates.join(", "))]
+    AmbiguousAsset {
+        /// The target the assets were matched for.
+        target: String,
+        /// The file names of the matching assets.
+        candidates: Vec<String>,
+    },
+    /// There is no release with the given tag.
+    #[error("there is no release with the tag `{tag}`")]
+    ReleaseNotFound {
+        /// The tag which was looked up.
+        tag: String,
+    },
+    /// None of the releases have a version matching the requested range.
+    #[error("no release matches the version `{req}`{}", closest_versions(closest))]
+    NoMatchingVersion {
+        /// The requested version range.
+        req: String,
+        /// The available versions closest to the range, newest first.
+        closest: Vec<String>,
+    },
+    /// The git tag of the release is not signed by a trusted signer.
+    #[error("the tag `{tag}` could not be verified: {reason}")]
+    TagNotVerified {
+        /// The tag which was checked.
+        tag: String,
+        /// Why the tag was not considered verified.
+        reason: String,
+    },
+    /// The release is marked as a pre-release, which `forbid_prerelease`
+    /// refuses to install.
+    #[error("the release `{tag}` is a pre-release, which is not allowed")]
+    PrereleaseNotAllowed {
+        /// The tag of the release.
+        tag: String,
+    },
+    /// The installed binary failed the smoke test, and was rolled back.
+    #[error("the binary for `{target}` failed the smoke test: {reason}")]
+    SmokeTestFailed {
+        /// The target of the asset the binary was taken from.
+        target: String,
+        /// How the smoke test failed.
+        reason: String,
+    },
+    /// The URL is not the URL of a GitHub release or release asset.
+    #[error("`{url}` is not the URL of a GitHub release")]
+    UnrecognizedReleaseUrl {
+        /// The URL which was given.
+        url: String,
+    },
+    /// A download URL, or the URL it redirects to, is not on one of the
+    /// allowed hosts.
+    #[error("refusing to download `{url}` since `{host}` is not an allowed host")]
+    UntrustedHost {
+        /// The URL which was going to be downloaded.
+        url: String,
+        /// The host of the URL.
+        host: String,
+    },
+    /// The downloaded binary is linked dynamically, but was required to be
+    /// linked statically.
+    #[error("the downloaded binary is not statically linked, it depends on {dependency}")]
+    NotStaticallyLinked {
+        /// What the binary depends on, such as the name of a library.
+        dependency: String,
+    },
+    /// No entry of the downloaded archive matches `require_archive_entry`,
+    /// so the asset is likely not the expected one.
+    #[error("no entry of the downloaded archive matches `{pattern}`")]
+    ExpectedEntryMissing {
+        /// The pattern which the entries were matched against.
+        pattern: String,
+    },
+    /// The SHA-256 digest of the downloaded asset does not match the
+    /// checksum published in the release.
+    #[error("the checksum of `{asset}` does not match, expected {expected} but got {actual}")]
+    ChecksumMismatch {
+        /// The file name of the asset.
+        asset: String,
+        /// The checksum published in the release.
+        expected: String,
+        /// The digest of the downloaded asset.
+        actual: String,
+    },
+    /// The minisign signature of the downloaded asset is missing or not
+    /// valid for the `pub_key`.
+    #[error("the signature of `{asset}` could not be verified: {reason}")]
+    BadSignature {
+        /// The file name of the asset.
+        asset: String,
+        /// Why the signature was rejected.
+        reason: String,
+    },
+    /// A request, including downloading its response, took longer than the
+    /// `request_timeout`.
+    #[error("a request timed out after {timeout:?}")]
+    RequestTimedOut {
+        /// The timeout which was exceeded.
+        timeout: Duration,
+    },
+    /// The check command did not finish within the `check_timeout`.
+    #[error("the check command timed out after {timeout:?}")]
+    CheckTimedOut {
+        /// The timeout which was exceeded.
+        timeout: Duration,
+    },
+    /// The installation has to be confirmed, but the user cannot be prompted
+    /// since stdin is not a terminal.
+    #[error(
+        "refusing to prompt for confirmation in a non-interactive context, set `no_confirm` to \
+         install without confirmation"
+    )]
+    NotInteractive,
+    /// Another process kept the target locked for longer than the lock
+    /// timeout.
+    #[error(
+        "`{}` is being updated by another process, gave up after {timeout:?}",
+        path.display()
+    )]
+    LockTimeout {
+        /// The target which was locked.
+        path: PathBuf,
+        /// How long was waited for the lock.
+        timeout: Duration,
+    },
+    /// An option was set for a feature which is not supported, such as
+    /// [`cosign_verify`](crate::BinswapGithubBuilder::cosign_verify).
+    #[error("{feature} is not supported")]
+    Unsupported {
+        /// The feature which was asked for.
+        feature: String,
+    },
+    /// The resolved version is older than the current version, and
+    /// `allow_downgrade` is not set.
+    #[error(
+        "refusing to downgrade from `{current}` to `{resolved}`, set `allow_downgrade` to allow it"
+    )]
+    Downgrade {
+        /// The version of the binary being replaced.
+        current: String,
+        /// The older version which was resolved.
+        resolved: String,
+    },
+}
+
+#[cfg(windows)]
+const ELEVATION_HINT: &str = "as Administrator";
+#[cfg(not(windows))]
+const ELEVATION_HINT: &str = "with `sudo`";
+
+fn size_range(min: Option<u64>, max: Option<u64>) -> String {
+    match (min, max) {
+        (Some(min), Some(max)) => format!("between {min} and {max} bytes"),
+        (Some(min), None) => format!("at least {min} bytes"),
+        (None, Some(max)) => format!("at most {max} bytes"),
+        (None, None) => "any size".to_string(),
+    }
+}
+
+fn targets_tried(tried: usize, total: usize) -> String {
+    if tried < total {
+        format!(", after trying {tried} of {total} targets")
+    } else {
+        String::new()
+    }
+}
+
+fn closest_versions(closest: &[String]) -> String {
+    if closest.is_empty() {
+        String::new()
+    } else {
+        format!(", the closest available are {}", closest.join(", "))
+    }
+}
+
+/// Returns whether elevating privileges could help with a permission error.
+pub(crate) fn can_elevate() -> bool {
+    #[cfg(unix)]
+    {
+        // SAFETY: `geteuid` is always successful and has no side effects.
+        unsafe { libc::geteuid() != 0 }
+    }
+    #[cfg(not(unix))]
+    {
+        true
+    }
+}
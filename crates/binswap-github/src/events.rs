@@ -0,0 +1,246 @@
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+use futures_util::{
+    future::{self, Either},
+    pin_mut, stream, Stream,
+};
+use serde_json::json;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{report::can_prompt, BinswapGithub, Error, SwapOutcome, SwapStatus};
+
+/// The phases of [`fetch_and_write_to_stream`](BinswapGithub::fetch_and_write_to_stream).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SwapEvent {
+    /// The latest version is being looked up.
+    ResolvingVersion,
+    /// The version to install has been determined.
+    VersionResolved(String),
+    /// Looking for a release asset for the target.
+    ProbingTarget(String),
+    /// A release asset was found for the target, and is being downloaded.
+    Downloading {
+        /// The target of the asset.
+        target: String,
+    },
+    /// The check command is being run on the downloaded binary.
+    Checking,
+    /// The installation is waiting to be confirmed, which is done through the
+    /// contained request.
+    AwaitingConfirmation(ConfirmRequest),
+    /// The binary has been written to the path.
+    Swapped(PathBuf),
+    /// The operation is done. This is always the last event.
+    Done(SwapOutcome),
+}
+
+/// How strongly the user has to confirm replacing the currently executed
+/// binary in-place.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfirmStrength {
+    /// Answering `y` or `yes` is enough.
+    #[default]
+    Normal,
+    /// The user has to type the `bin_name`, or the word `update`.
+    Strong,
+}
+
+/// What is about to be installed, passed to
+/// [`confirm_with`](crate::BinswapGithubBuilder::confirm_with).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ConfirmContext {
+    /// The path the binary is about to be written to.
+    pub path: PathBuf,
+    /// The version which is about to be installed.
+    pub version: String,
+}
+
+/// A request for confirming the installation of a binary. Dropping the
+/// request declines the installation.
+#[derive(Debug)]
+pub struct ConfirmRequest {
+    /// The path the binary is about to be written to.
+    pub path: PathBuf,
+    /// How strongly the installation should be confirmed, which is
+    /// [`Strong`](ConfirmStrength::Strong) only when replacing the currently
+    /// executed binary with `confirm_strength` set to it.
+    pub strength: ConfirmStrength,
+    answer: oneshot::Sender<bool>,
+}
+
+impl ConfirmRequest {
+    /// Answers whether to proceed with the installation.
+    pub fn respond(self, proceed: bool) {
+        self.answer.send(proceed).ok();
+    }
+}
+
+impl BinswapGithub {
+    /// Like [`fetch_and_write_to`](Self::fetch_and_write_to), but returns a
+    /// stream of the phases of the operation rather than printing them. The
+    /// operation only progresses while the stream is polled.
+    ///
+    /// Unless `no_confirm` is set, the operation pauses at
+    /// [`SwapEvent::AwaitingConfirmation`] until the contained request is
+    /// responded to.
+    pub fn fetch_and_write_to_stream(
+        &self,
+        target_binary: impl AsRef<Path>,
+    ) -> impl Stream<Item = Result<SwapEvent>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut this = self.clone();
+        this.event_sender = Some(tx);
+        let target_binary = target_binary.as_ref().to_path_buf();
+
+        let run = Box::pin(async move { this.fetch_and_write_to(target_binary).await });
+
+        enum State<F> {
+            Running(F, mpsc::UnboundedReceiver<SwapEvent>),
+            Draining(Box<Result<SwapOutcome>>, mpsc::UnboundedReceiver<SwapEvent>),
+            Done,
+        }
+
+        stream::unfold(State::Running(run, rx), |state| async move {
+            match state {
+                State::Running(mut run, mut rx) => {
+                    let next = {
+                        let recv = rx.recv();
+                        pin_mut!(recv);
+                        match future::select(run.as_mut(), recv).await {
+                            Either::Left((res, _)) => Either::Left(res),
+                            Either::Right((event, _)) => Either::Right(event),
+                        }
+                    };
+                    match next {
+                        Either::Right(Some(event)) => Some((Ok(event), State::Running(run, rx))),
+                        Either::Right(None) => Some((run.await.map(SwapEvent::Done), State::Done)),
+                        Either::Left(res) => match rx.recv().await {
+                            Some(event) => Some((Ok(event), State::Draining(Box::new(res), rx))),
+                            None => Some((res.map(SwapEvent::Done), State::Done)),
+                        },
+                    }
+                }
+                State::Draining(res, mut rx) => match rx.recv().await {
+                    Some(event) => Some((Ok(event), State::Draining(res, rx))),
+                    None => Some(((*res).map(SwapEvent::Done), State::Done)),
+                },
+                State::Done => None,
+            }
+        })
+    }
+
+    /// Reports a phase of the operation, if it is being streamed, or to
+    /// `on_progress` or as JSON if configured.
+    pub(crate) fn emit(&self, event: SwapEvent) {
+        self.print_json_event(&event);
+        self.progress_of(&event);
+        if let Some(tx) = &self.event_sender {
+            tx.send(event).ok();
+        }
+    }
+
+    /// Prints `event` to stdout as a line of JSON, if `json_events` is set.
+    pub(crate) fn print_json_event(&self, event: &SwapEvent) {
+        if !self.json_events {
+            return;
+        }
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "{}", json_event(event)).ok();
+        stdout.flush().ok();
+    }
+
+    /// Asks whether to proceed with writing `version` to `path`, either
+    /// through `confirm_with`, the stream or by prompting the user. If stdin
+    /// is not a terminal, the user cannot be prompted, which fails with
+    /// [`Error::NotInteractive`] unless `assume_yes_when_noninteractive` is
+    /// set.
+    pub(crate) async fn confirm(&self, path: &Path, version: &str) -> Result<bool> {
+        if self.no_confirm {
+            return Ok(true);
+        }
+        if let Some(confirm_with) = &self.confirm_with {
+            let context = ConfirmContext {
+                path: path.to_path_buf(),
+                version: version.to_string(),
+            };
+            return Ok(confirm_with(&context).await);
+        }
+
+        let strength = if self.in_place {
+            self.confirm_strength
+        } else {
+            ConfirmStrength::Normal
+        };
+        match &self.event_sender {
+            Some(tx) => {
+                let (answer, rx) = oneshot::channel();
+                let request = ConfirmRequest {
+                    path: path.to_path_buf(),
+                    strength,
+                    answer,
+                };
+                tx.send(SwapEvent::AwaitingConfirmation(request)).ok();
+                Ok(rx.await.unwrap_or(false))
+            }
+            None if !can_prompt() => self.noninteractive_answer(),
+            None => Ok(match strength {
+                ConfirmStrength::Normal => crate::confirm(None).await,
+                ConfirmStrength::Strong => crate::confirm(Some(self.bin_name.clone())).await,
+            }),
+        }
+    }
+
+    /// The answer to a confirmation when the user cannot be prompted.
+    pub(crate) fn noninteractive_answer(&self) -> Result<bool> {
+        if self.assume_yes_when_noninteractive {
+            Ok(true)
+        } else {
+            Err(Error::NotInteractive.into())
+        }
+    }
+}
+
+/// The JSON object describing `event`, whose `event` field names the kind of
+/// event.
+fn json_event(event: &SwapEvent) -> serde_json::Value {
+    match event {
+        SwapEvent::ResolvingVersion => json!({ "event": "resolving_version" }),
+        SwapEvent::VersionResolved(version) => {
+            json!({ "event": "version_resolved", "version": version })
+        }
+        SwapEvent::ProbingTarget(target) => json!({ "event": "probing_target", "target": target }),
+        SwapEvent::Downloading { target } => json!({ "event": "downloading", "target": target }),
+        SwapEvent::Checking => json!({ "event": "checking" }),
+        SwapEvent::AwaitingConfirmation(request) => json!({
+            "event": "awaiting_confirmation",
+            "path": request.path,
+        }),
+        SwapEvent::Swapped(path) => json!({ "event": "swapped", "path": path }),
+        SwapEvent::Done(outcome) => {
+            let (status, reason) = match &outcome.status {
+                SwapStatus::Swapped => ("swapped", None),
+                SwapStatus::Declined => ("declined", None),
+                SwapStatus::Skipped { reason } => ("skipped", Some(reason.to_string())),
+            };
+            json!({
+                "event": "done",
+                "version": outcome.version,
+                "target": outcome.target_used,
+                "path": outcome.installed_path,
+                "bytes_written": outcome.bytes_written,
+                "bytes_downloaded": outcome.bytes_downloaded,
+                "download_ms": outcome.download_duration.map(|d| d.as_millis() as u64),
+                "dry_run": outcome.dry_run,
+                "status": status,
+                "reason": reason,
+            })
+        }
+    }
+}
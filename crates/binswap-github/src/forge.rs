@@ -0,0 +1,156 @@
+use color_eyre::{eyre::eyre, Result};
+use serde::Deserialize;
+
+use crate::{AssetInfo, BinswapGithub, ReleaseInfo};
+
+/// The kind of forge the repository is hosted on, which determines how its
+/// releases are looked up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ForgeKind {
+    /// GitHub, or GitHub Enterprise Server.
+    #[default]
+    GitHub,
+    /// GitLab, or a self-managed GitLab instance. The assets of a release are
+    /// the links attached to it, and are matched by their names, since they
+    /// can be hosted anywhere.
+    GitLab,
+    /// Gitea or Forgejo, such as Codeberg or a self-hosted instance, whose
+    /// API for releases is a subset of the one of GitHub.
+    Gitea,
+}
+
+impl ForgeKind {
+    /// The name of the forge, as shown in messages.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "GitHub",
+            ForgeKind::GitLab => "GitLab",
+            ForgeKind::Gitea => "Gitea",
+        }
+    }
+
+    fn default_api_base_url(self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "https://api.github.com",
+            ForgeKind::GitLab => "https://gitlab.com/api/v4",
+            ForgeKind::Gitea => "https://codeberg.org/api/v1",
+        }
+    }
+
+    fn default_download_base_url(self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "https://github.com",
+            ForgeKind::GitLab => "https://gitlab.com",
+            ForgeKind::Gitea => "https://codeberg.org",
+        }
+    }
+}
+
+/// A release as returned by the GitLab API.
+#[derive(Debug, Deserialize)]
+struct GitLabRelease {
+    tag_name: String,
+    name: Option<String>,
+    description: Option<String>,
+    created_at: Option<String>,
+    released_at: Option<String>,
+    #[serde(default)]
+    upcoming_release: bool,
+    #[serde(rename = "_links")]
+    links: Option<GitLabReleaseLinks>,
+    assets: GitLabAssets,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabReleaseLinks {
+    #[serde(rename = "self")]
+    html: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabAssets {
+    #[serde(default)]
+    links: Vec<GitLabLink>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabLink {
+    name: String,
+    url: String,
+    direct_asset_url: Option<String>,
+}
+
+impl From<GitLabRelease> for ReleaseInfo {
+    fn from(release: GitLabRelease) -> Self {
+        ReleaseInfo {
+            tag_name: release.tag_name,
+            name: release.name,
+            body: release.description,
+            draft: false,
+            prerelease: release.upcoming_release,
+            created_at: release.created_at,
+            published_at: release.released_at,
+            html_url: release
+                .links
+                .and_then(|links| links.html)
+                .unwrap_or_default(),
+            assets: release
+                .assets
+                .links
+                .into_iter()
+                .map(|link| AssetInfo {
+                    name: link.name,
+                    browser_download_url: link.direct_asset_url.unwrap_or(link.url),
+                    content_type: String::new(),
+                    size: 0,
+                    updated_at: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl BinswapGithub {
+    /// The base URL of the API of the forge.
+    pub(crate) fn api_base_url(&self) -> &str {
+        self.api_base_url
+            .as_deref()
+            .unwrap_or(self.forge.default_api_base_url())
+    }
+
+    /// The base URL of repositories on the forge.
+    pub(crate) fn download_base_url(&self) -> &str {
+        self.download_base_url
+            .as_deref()
+            .unwrap_or(self.forge.default_download_base_url())
+    }
+
+    /// Fails unless the repository is on GitHub, for features only GitHub
+    /// supports.
+    pub(crate) fn ensure_github(&self, feature: &str) -> Result<()> {
+        if self.forge != ForgeKind::GitHub {
+            return Err(eyre!("{feature} is not supported on {}", self.forge.name()));
+        }
+        Ok(())
+    }
+
+    /// Parses a release from a response of the API of the forge.
+    pub(crate) fn parse_release(&self, body: &[u8]) -> Result<ReleaseInfo> {
+        Ok(match self.forge {
+            ForgeKind::GitHub | ForgeKind::Gitea => serde_json::from_slice(body)?,
+            ForgeKind::GitLab => serde_json::from_slice::<GitLabRelease>(body)?.into(),
+        })
+    }
+
+    /// Parses a list of releases from a response of the API of the forge.
+    pub(crate) fn parse_releases(&self, body: &[u8]) -> Result<Vec<ReleaseInfo>> {
+        Ok(match self.forge {
+            ForgeKind::GitHub | ForgeKind::Gitea => serde_json::from_slice(body)?,
+            ForgeKind::GitLab => serde_json::from_slice::<Vec<GitLabRelease>>(body)?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use binstalk::helpers::remote::Client;
+use color_eyre::{eyre::eyre, Result};
+use futures_util::{stream, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{release::MAX_CONCURRENT_REQUESTS, AssetInfo, BinswapGithub, ForgeKind, ReleaseInfo};
+
+/// The fields of a release selected in queries, matching [`Release`].
+const RELEASE_FIELDS: &str = "
+    tagName name description isDraft isPrerelease createdAt publishedAt url
+    releaseAssets(first: 100) {
+        nodes { name downloadUrl contentType size updatedAt }
+    }
+";
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    data: Option<HashMap<String, Option<Repository>>>,
+    #[serde(default)]
+    errors: Vec<ResponseError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Repository {
+    latest_release: Option<Release>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Release {
+    tag_name: String,
+    name: Option<String>,
+    description: Option<String>,
+    is_draft: bool,
+    is_prerelease: bool,
+    created_at: Option<String>,
+    published_at: Option<String>,
+    url: String,
+    release_assets: Nodes<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Nodes<T> {
+    nodes: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Asset {
+    name: String,
+    download_url: String,
+    content_type: String,
+    size: u64,
+    updated_at: Option<String>,
+}
+
+impl From<Release> for ReleaseInfo {
+    fn from(release: Release) -> Self {
+        ReleaseInfo {
+            tag_name: release.tag_name,
+            name: release.name,
+            body: release.description,
+            draft: release.is_draft,
+            prerelease: release.is_prerelease,
+            created_at: release.created_at,
+            published_at: release.published_at,
+            html_url: release.url,
+            assets: release
+                .release_assets
+                .nodes
+                .into_iter()
+                .map(|asset| AssetInfo {
+                    name: asset.name,
+                    browser_download_url: asset.download_url,
+                    content_type: asset.content_type,
+                    size: asset.size,
+                    updated_at: asset.updated_at,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl BinswapGithub {
+    /// The URL of the GraphQL API. On GitHub Enterprise Server it is next to
+    /// the REST API, at `/api/graphql` rather than `/api/v3/graphql`.
+    fn graphql_url(&self) -> String {
+        let base = self.api_base_url().trim_end_matches('/');
+        match base.strip_suffix("/v3") {
+            Some(api) => format!("{api}/graphql"),
+            None => format!("{base}/graphql"),
+        }
+    }
+
+    /// Fetches the latest releases of the repositories given as pairs of
+    /// author and name, in a single request to the GraphQL API of GitHub.
+    /// A repository whose release could not be fetched results in an error in
+    /// its place rather than failing the whole batch.
+    ///
+    /// The GraphQL API requires a token. Without one, or if the GraphQL
+    /// request fails, the releases are fetched from the REST API instead.
+    pub async fn fetch_latest_releases(
+        &self,
+        repos: &[(String, String)],
+    ) -> Result<Vec<Result<ReleaseInfo>>> {
+        if repos.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let this = &self.fresh_budget();
+        let client = this.client()?;
+
+        if this.forge == ForgeKind::GitHub && this.token().is_some() {
+            if let Ok(releases) = this.graphql_latest_releases(&client, repos).await {
+                return Ok(releases);
+            }
+        }
+
+        Ok(stream::iter(repos)
+            .map(|(author, name)| {
+                let mut repo = this.clone();
+                repo.repo_author = author.clone();
+                repo.repo_name = name.clone();
+                let client = client.clone();
+                async move { repo.fetch_latest_release_rest(&client).await }
+            })
+            .buffered(MAX_CONCURRENT_REQUESTS)
+            .collect()
+            .await)
+    }
+
+    /// Fetches the latest release of the repository from the GraphQL API.
+    pub(crate) async fn graphql_latest_release(&self, client: &Client) -> Result<ReleaseInfo> {
+        let repos = [(self.repo_author.clone(), self.repo_name.clone())];
+        self.graphql_latest_releases(client, &repos)
+            .await?
+            .remove(0)
+    }
+
+    async fn graphql_latest_releases(
+        &self,
+        client: &Client,
+        repos: &[(String, String)],
+    ) -> Result<Vec<Result<ReleaseInfo>>> {
+        let token = self
+            .token()
+            .ok_or_else(|| eyre!("the GraphQL API requires a token"))?;
+
+        let mut parameters = vec![];
+        let mut selections = vec![];
+        let mut variables = serde_json::Map::new();
+        for (i, (author, name)) in repos.iter().enumerate() {
+            parameters.push(format!("$owner{i}: String!, $name{i}: String!"));
+            selections.push(format!(
+                "repo{i}: repository(owner: $owner{i}, name: $name{i}) {{ latestRelease {{ {RELEASE_FIELDS} }} }}"
+            ));
+            variables.insert(format!("owner{i}"), author.clone().into());
+            variables.insert(format!("name{i}"), name.clone().into());
+        }
+        let body = json!({
+            "query": format!("query({}) {{ {} }}", parameters.join(", "), selections.join(" ")),
+            "variables": variables,
+        })
+        .to_string();
+
+        let res: Response = self
+            .retrying(|| async {
+                let bytes = client
+                    .get_inner()
+                    .post(self.graphql_url())
+                    .bearer_auth(&token)
+                    .header("Content-Type", "application/json")
+                    .body(body.clone())
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await?;
+                Ok(serde_json::from_slice(&bytes)?)
+            })
+            .await?;
+
+        let errors: Vec<String> = res.errors.into_iter().map(|e| e.message).collect();
+        let Some(mut data) = res.data else {
+            return Err(eyre!("the GraphQL request failed: {}", errors.join(", ")));
+        };
+
+        Ok(repos
+            .iter()
+            .enumerate()
+            .map(
+                |(i, (author, name))| match data.remove(&format!("repo{i}")) {
+                    Some(Some(Repository {
+                        latest_release: Some(release),
+                    })) => Ok(release.into()),
+                    Some(Some(Repository {
+                        latest_release: None,
+                    })) => Err(eyre!("`{author}/{name}` has no releases")),
+                    _ => Err(eyre!(
+                        "could not fetch `{author}/{name}`: {}",
+                        errors.join(", ")
+                    )),
+                },
+            )
+            .collect())
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{eyre::Context, Result};
+
+use crate::{
+    report::{ExecutableCommand, Print, ResetColor, Stylize},
+    swap, BinswapGithub,
+};
+
+impl BinswapGithub {
+    /// Marks a [`provisional`](crate::BinswapGithubBuilder::provisional)
+    /// update of the currently executed binary as healthy, which discards the
+    /// previous binary kept for rolling back. This is meant to be called by
+    /// the new binary once it is known to work.
+    pub async fn confirm_healthy(&self) -> Result<()> {
+        let target_binary = self.current_exe()?;
+        remove_if_exists(&backup_path(&target_binary)).await?;
+        remove_if_exists(&pending_path(&target_binary)).await?;
+        Ok(())
+    }
+
+    /// Restores the previous binary at `target_binary` if it was updated
+    /// provisionally and never confirmed healthy. Returns the version which
+    /// was rolled back, if any.
+    pub(crate) async fn roll_back_unhealthy(
+        &self,
+        target_binary: &Path,
+        temp: &Path,
+    ) -> Result<Option<String>> {
+        if !self.provisional || self.dry_run {
+            return Ok(None);
+        }
+
+        let pending = pending_path(target_binary);
+        let version = match tokio::fs::read_to_string(&pending).await {
+            Ok(version) => version.trim().to_string(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).wrap_err("failed to read the pending update"),
+        };
+
+        let _lock = self.lock(target_binary).await?;
+        swap(
+            &backup_path(target_binary),
+            target_binary,
+            &temp.join("unhealthy-binary"),
+        )
+        .await
+        .wrap_err("failed to roll back to the previous binary")?;
+        remove_if_exists(&pending).await?;
+
+        self.stderr()
+            .execute(Print("Rolled back ".yellow()))?
+            .execute(Print(format!("`{}`", target_binary.display())))?
+            .execute(Print(" since version ".yellow()))?
+            .execute(Print(&version))?
+            .execute(Print(" was not confirmed healthy\n".yellow()))?
+            .execute(ResetColor)?;
+
+        Ok(Some(version))
+    }
+
+    /// Records that `target_binary` was updated to `version`, and must be
+    /// confirmed healthy to not be rolled back. Nothing is recorded if there
+    /// was no previous binary to roll back to.
+    pub(crate) async fn mark_pending(&self, target_binary: &Path, version: &str) -> Result<()> {
+        if !tokio::fs::try_exists(backup_path(target_binary)).await? {
+            return Ok(());
+        }
+        tokio::fs::write(pending_path(target_binary), version)
+            .await
+            .wrap_err("failed to record the pending update")
+    }
+}
+
+/// Where the previous binary is kept during a provisional update.
+pub(crate) fn backup_path(target_binary: &Path) -> PathBuf {
+    sibling(target_binary, "binswap-backup")
+}
+
+/// The file recording the version of a provisional update which has not been
+/// confirmed healthy yet.
+fn pending_path(target_binary: &Path) -> PathBuf {
+    sibling(target_binary, "binswap-pending")
+}
+
+pub(crate) fn sibling(target_binary: &Path, extension: &str) -> PathBuf {
+    let name = target_binary
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    target_binary.with_file_name(format!(".{name}.{extension}"))
+}
+
+pub(crate) async fn remove_if_exists(path: &Path) -> Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            Err(e).wrap_err_with(|| format!("failed to remove `{}`", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,473 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+    process::Command,
+};
+
+use binstalk::get_desired_targets;
+
+use crate::{
+    report::{ExecutableCommand, Print, Stylize},
+    BinswapGithub,
+};
+
+const PT_LOAD: u32 = 1;
+const LC_VERSION_MIN_MACOSX: u32 = 0x24;
+const LC_BUILD_VERSION: u32 = 0x32;
+const PLATFORM_MACOS: u32 = 1;
+
+/// A macOS version as major, minor and patch.
+type MacosVersion = (u32, u32, u32);
+
+impl BinswapGithub {
+    /// Whether the host uses the hard-float ABI, which only matters on
+    /// 32-bit ARM.
+    fn hard_float(&self) -> Option<bool> {
+        self.host_hard_float.or_else(detect_hard_float)
+    }
+
+    /// The memory page size of the host in bytes.
+    fn page_size(&self) -> Option<u64> {
+        self.host_page_size.or_else(detect_page_size)
+    }
+
+    /// The targets to look for assets of, in order, and how many candidate
+    /// targets there were before `max_targets_to_try` was applied.
+    pub(crate) async fn candidate_targets(&self) -> (Vec<String>, usize) {
+        let mut targets = if let Some(targets) = self.targets.clone() {
+            targets
+        } else {
+            self.rank_targets(get_desired_targets(None).get().await.to_vec())
+        };
+        let total = targets.len();
+        if let Some(max) = self.max_targets_to_try {
+            targets.truncate(max);
+        }
+        (targets, total)
+    }
+
+    /// The version of macOS on the host, if it is macOS.
+    fn macos_version(&self) -> Option<MacosVersion> {
+        match &self.host_macos_version {
+            Some(version) => parse_macos_version(version),
+            None => detect_macos_version(),
+        }
+    }
+
+    /// Orders `targets` by how well they fit the host. On 32-bit ARM,
+    /// targets of the other float ABI are moved last, and hard-float targets
+    /// are left out on soft-float hosts since they cannot run there.
+    pub(crate) fn rank_targets(&self, targets: Vec<String>) -> Vec<String> {
+        let Some(hard_float) = self.hard_float() else {
+            return targets;
+        };
+
+        let mut ranked = vec![];
+        for target in targets {
+            if !is_arm32(&target) {
+                ranked.push(target);
+                continue;
+            }
+            let is_hard_float = target.ends_with("hf");
+            if is_hard_float && !hard_float {
+                let soft_float = target.trim_end_matches("hf").to_string();
+                if !ranked.contains(&soft_float) {
+                    ranked.push(soft_float);
+                }
+            } else if !ranked.contains(&target) {
+                ranked.push(target);
+            }
+        }
+        ranked.sort_by_key(|target| is_arm32(target) && target.ends_with("hf") != hard_float);
+        ranked
+    }
+
+    /// Whether the binary at `path` can be loaded on the host. ELF binaries
+    /// whose segments are aligned to less than the page size of the host
+    /// cannot be loaded, which is common for `aarch64` binaries on hosts
+    /// with 16K or 64K pages.
+    ///
+    /// Mach-O binaries requiring a newer version of macOS than the host's
+    /// cannot be loaded either.
+    pub(crate) fn runs_on_host(&self, path: &Path) -> bool {
+        let page_size_fits = match (self.page_size(), elf_program_headers(path)) {
+            (Some(page_size), Some(headers)) => headers
+                .into_iter()
+                .filter(|header| header.kind == PT_LOAD)
+                .all(|header| header.align <= 1 || header.align >= page_size),
+            _ => true,
+        };
+        page_size_fits && self.macos_version_fits(path)
+    }
+
+    fn macos_version_fits(&self, path: &Path) -> bool {
+        let Some(required) = macho_min_macos_version(path) else {
+            return true;
+        };
+        let Some(host) = self.macos_version() else {
+            return true;
+        };
+        if required <= host {
+            return true;
+        }
+
+        let format = |(major, minor, _): MacosVersion| format!("{major}.{minor}");
+        self.stderr()
+            .execute(Print(
+                format!(
+                    " > The binary requires macOS {}, but the host has {}\n",
+                    format(required),
+                    format(host)
+                )
+                .yellow()
+                .italic(),
+            ))
+            .ok();
+        false
+    }
+}
+
+fn is_arm32(target: &str) -> bool {
+    (target.starts_with("arm") || target.starts_with("thumb")) && target.contains("eabi")
+}
+
+fn parse_macos_version(version: &str) -> Option<MacosVersion> {
+    let mut parts = version.trim().split('.').map(str::parse::<u32>);
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+fn detect_macos_version() -> Option<MacosVersion> {
+    if !cfg!(target_os = "macos") {
+        return None;
+    }
+    let output = Command::new("sw_vers")
+        .arg("-productVersion")
+        .output()
+        .ok()?;
+    parse_macos_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Determines the float ABI from the dynamic loader installed on the host.
+fn detect_hard_float() -> Option<bool> {
+    if !cfg!(all(target_os = "linux", target_arch = "arm")) {
+        return None;
+    }
+    let exists = |path: &str| Path::new(path).exists();
+    if exists("/lib/ld-linux-armhf.so.3") || exists("/lib/ld-musl-armhf.so.1") {
+        Some(true)
+    } else if exists("/lib/ld-linux.so.3") || exists("/lib/ld-musl-arm.so.1") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn detect_page_size() -> Option<u64> {
+    #[cfg(unix)]
+    {
+        // SAFETY: `sysconf` has no side effects.
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        u64::try_from(page_size)
+            .ok()
+            .filter(|&page_size| page_size > 0)
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// A program header of an ELF file, describing a segment.
+pub(crate) struct ProgramHeader {
+    /// The type of the segment, such as `PT_LOAD`.
+    pub(crate) kind: u32,
+    /// The alignment of the segment.
+    pub(crate) align: u64,
+}
+
+/// The program headers of the ELF file at `path`, or `None` if it is not an
+/// ELF file.
+pub(crate) fn elf_program_headers(path: &Path) -> Option<Vec<ProgramHeader>> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0; 64];
+    file.read_exact(&mut header).ok()?;
+    if &header[..4] != b"\x7fELF" {
+        return None;
+    }
+    let is_64 = header[4] == 2;
+    let is_le = header[5] == 1;
+
+    let read = |bytes: &[u8], offset: usize, len: usize| -> Option<u64> {
+        let field = bytes.get(offset..offset + len)?;
+        let mut value = 0;
+        for i in 0..len {
+            let byte = if is_le { field[len - 1 - i] } else { field[i] };
+            value = value << 8 | u64::from(byte);
+        }
+        Some(value)
+    };
+
+    let (phoff, phentsize, phnum) = if is_64 {
+        (
+            read(&header, 0x20, 8)?,
+            read(&header, 0x36, 2)?,
+            read(&header, 0x38, 2)?,
+        )
+    } else {
+        (
+            read(&header, 0x1c, 4)?,
+            read(&header, 0x2a, 2)?,
+            read(&header, 0x2c, 2)?,
+        )
+    };
+
+    // The headers must hold the fields read below, and fit in the file.
+    let min_phentsize = if is_64 { 0x38 } else { 0x20 };
+    if phentsize < min_phentsize {
+        return None;
+    }
+    let len = phentsize.checked_mul(phnum)?;
+    if phoff.checked_add(len)? > file.metadata().ok()?.len() {
+        return None;
+    }
+
+    let mut headers = vec![0; usize::try_from(len).ok()?];
+    file.seek(SeekFrom::Start(phoff)).ok()?;
+    file.read_exact(&mut headers).ok()?;
+
+    let mut program_headers = vec![];
+    for header in headers.chunks_exact(usize::try_from(phentsize).ok()?) {
+        program_headers.push(ProgramHeader {
+            kind: read(header, 0, 4)? as u32,
+            align: if is_64 {
+                read(header, 0x30, 8)?
+            } else {
+                read(header, 0x1c, 4)?
+            },
+        });
+    }
+    Some(program_headers)
+}
+
+/// The minimum version of macOS required by the Mach-O file at `path`, or
+/// `None` if it is not a Mach-O file or does not say. For universal binaries
+/// the lowest requirement of the slices is used.
+fn macho_min_macos_version(path: &Path) -> Option<MacosVersion> {
+    let read = |bytes: &[u8], offset: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            bytes.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+    let version = |encoded: u32| (encoded >> 16, (encoded >> 8) & 0xff, encoded & 0xff);
+
+    macho_load_commands(path)?
+        .into_iter()
+        .filter_map(|commands| {
+            commands.iter().find_map(|command| match command.kind {
+                LC_BUILD_VERSION if read(&command.data, 8)? == PLATFORM_MACOS => {
+                    Some(version(read(&command.data, 12)?))
+                }
+                LC_VERSION_MIN_MACOSX => Some(version(read(&command.data, 8)?)),
+                _ => None,
+            })
+        })
+        .min()
+}
+
+/// A load command of a Mach-O file.
+pub(crate) struct LoadCommand {
+    /// The type of the command, such as `LC_LOAD_DYLIB`.
+    pub(crate) kind: u32,
+    /// The whole command, including the type and size.
+    pub(crate) data: Vec<u8>,
+}
+
+/// The load commands of each slice of the Mach-O file at `path`, of which
+/// universal binaries have several, or `None` if it is not a Mach-O file.
+pub(crate) fn macho_load_commands(path: &Path) -> Option<Vec<Vec<LoadCommand>>> {
+    let mut file = File::open(path).ok()?;
+    let mut magic = [0; 4];
+    file.read_exact(&mut magic).ok()?;
+
+    if magic == [0xca, 0xfe, 0xba, 0xbe] {
+        let mut count = [0; 4];
+        file.read_exact(&mut count).ok()?;
+        let count = u32::from_be_bytes(count);
+        // Java class files share the magic, but have a large version here.
+        if count > 64 {
+            return None;
+        }
+        let archs_size = 20 * u64::from(count);
+        if 8 + archs_size > file.metadata().ok()?.len() {
+            return None;
+        }
+        let mut archs = vec![0; usize::try_from(archs_size).ok()?];
+        file.read_exact(&mut archs).ok()?;
+        return archs
+            .chunks_exact(20)
+            .map(|arch| {
+                let offset = u32::from_be_bytes(arch[8..12].try_into().unwrap());
+                macho_slice_load_commands(&mut file, u64::from(offset))
+            })
+            .collect();
+    }
+
+    Some(vec![macho_slice_load_commands(&mut file, 0)?])
+}
+
+fn macho_slice_load_commands(file: &mut File, offset: u64) -> Option<Vec<LoadCommand>> {
+    let mut header = [0; 32];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut header).ok()?;
+    let header_size = match header[..4] {
+        [0xce, 0xfa, 0xed, 0xfe] => 28,
+        [0xcf, 0xfa, 0xed, 0xfe] => 32,
+        _ => return None,
+    };
+    let read = |bytes: &[u8], offset: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            bytes.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+    let commands_size = read(&header, 20)?;
+    let commands_offset = offset.checked_add(header_size)?;
+    if commands_offset.checked_add(u64::from(commands_size))? > file.metadata().ok()?.len() {
+        return None;
+    }
+
+    let mut commands = vec![0; usize::try_from(commands_size).ok()?];
+    file.seek(SeekFrom::Start(commands_offset)).ok()?;
+    file.read_exact(&mut commands).ok()?;
+
+    let mut load_commands = vec![];
+    let mut rest = &commands[..];
+    while rest.len() >= 8 {
+        let kind = read(rest, 0)?;
+        let size = read(rest, 4)? as usize;
+        if size < 8 || size > rest.len() {
+            return None;
+        }
+        load_commands.push(LoadCommand {
+            kind,
+            data: rest[..size].to_vec(),
+        });
+        rest = &rest[size..];
+    }
+    Some(load_commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A little-endian ELF64 file with `phnum` program headers of
+    /// `phentsize` bytes after the file header, of which `headers` are
+    /// written.
+    fn elf64(phentsize: u16, phnum: u16, headers: &[u8]) -> tempfile::NamedTempFile {
+        let mut bytes = vec![0; 64];
+        bytes[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        bytes[0x20..0x28].copy_from_slice(&64u64.to_le_bytes());
+        bytes[0x36..0x38].copy_from_slice(&phentsize.to_le_bytes());
+        bytes[0x38..0x3a].copy_from_slice(&phnum.to_le_bytes());
+        bytes.extend_from_slice(headers);
+        file_with(&bytes)
+    }
+
+    /// A 64-bit Mach-O file declaring `commands_size` bytes of load
+    /// commands, of which `commands` are written.
+    fn macho(commands_size: u32, commands: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0; 32];
+        bytes[..4].copy_from_slice(&[0xcf, 0xfa, 0xed, 0xfe]);
+        bytes[20..24].copy_from_slice(&commands_size.to_le_bytes());
+        bytes.extend_from_slice(commands);
+        bytes
+    }
+
+    /// An `LC_BUILD_VERSION` command requiring macOS 11.0.
+    fn build_version() -> Vec<u8> {
+        [LC_BUILD_VERSION, 24, PLATFORM_MACOS, 11 << 16, 0, 0]
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect()
+    }
+
+    fn file_with(bytes: &[u8]) -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), bytes).unwrap();
+        file
+    }
+
+    #[test]
+    fn macho_load_commands_are_read() {
+        let file = file_with(&macho(24, &build_version()));
+        assert_eq!(macho_min_macos_version(file.path()), Some((11, 0, 0)));
+    }
+
+    #[test]
+    fn macho_load_commands_reject_truncated_headers() {
+        let file = file_with(&macho(24, &build_version())[..20]);
+        assert!(macho_load_commands(file.path()).is_none());
+
+        let file = file_with(&macho(48, &build_version()));
+        assert!(macho_load_commands(file.path()).is_none());
+    }
+
+    #[test]
+    fn macho_load_commands_reject_huge_headers() {
+        let file = file_with(&macho(u32::MAX, &build_version()));
+        assert!(macho_load_commands(file.path()).is_none());
+    }
+
+    #[test]
+    fn macho_fat_headers_are_bounded_by_the_file() {
+        let slice = macho(24, &build_version());
+        let mut fat = vec![0xca, 0xfe, 0xba, 0xbe];
+        fat.extend_from_slice(&1u32.to_be_bytes());
+        fat.extend_from_slice(&[0; 8]);
+        fat.extend_from_slice(&28u32.to_be_bytes());
+        fat.extend_from_slice(&(slice.len() as u32).to_be_bytes());
+        fat.extend_from_slice(&[0; 4]);
+        fat.extend_from_slice(&slice);
+        let file = file_with(&fat);
+        assert_eq!(macho_min_macos_version(file.path()), Some((11, 0, 0)));
+
+        // Declares more architectures than the file holds.
+        fat[4..8].copy_from_slice(&64u32.to_be_bytes());
+        let file = file_with(&fat);
+        assert!(macho_load_commands(file.path()).is_none());
+    }
+
+    #[test]
+    fn elf_program_headers_are_read() {
+        let mut header = vec![0; 0x38];
+        header[..4].copy_from_slice(&PT_LOAD.to_le_bytes());
+        header[0x30..].copy_from_slice(&0x4000u64.to_le_bytes());
+        let file = elf64(0x38, 1, &header);
+
+        let headers = elf_program_headers(file.path()).unwrap();
+        assert_eq!(headers.len(), 1);
+        assert_eq!((headers[0].kind, headers[0].align), (PT_LOAD, 0x4000));
+    }
+
+    #[test]
+    fn elf_program_headers_reject_zero_sized_entries() {
+        let file = elf64(0, 4, &[]);
+        assert!(elf_program_headers(file.path()).is_none());
+    }
+
+    #[test]
+    fn elf_program_headers_reject_short_entries() {
+        let file = elf64(0x20, 1, &[0; 0x20]);
+        assert!(elf_program_headers(file.path()).is_none());
+    }
+
+    #[test]
+    fn elf_program_headers_reject_headers_beyond_the_file() {
+        let file = elf64(0xffff, 0xffff, &[0; 0x38]);
+        assert!(elf_program_headers(file.path()).is_none());
+    }
+}
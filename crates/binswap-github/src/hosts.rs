@@ -0,0 +1,68 @@
+use binstalk::helpers::remote::{Client, Response, Url};
+use color_eyre::Result;
+
+use crate::{BinswapGithub, Error};
+
+impl BinswapGithub {
+    /// Sends the request downloading `url`, failing with
+    /// [`Error::UntrustedHost`] unless both `url` and the URL the response
+    /// was finally served from, after any redirects, are on one of the
+    /// `allowed_download_hosts`, if any are given. This is checked on the
+    /// response of the download itself, before any of its body is read.
+    pub(crate) async fn get_download(&self, client: &Client, url: &Url) -> Result<Response> {
+        self.check_host(url)?;
+        let response = client.get(url.clone()).send(true).await?;
+        self.check_host(response.url())?;
+        Ok(response)
+    }
+
+    fn check_host(&self, url: &Url) -> Result<()> {
+        let Some(allowed) = &self.allowed_download_hosts else {
+            return Ok(());
+        };
+
+        let host = url.host_str().unwrap_or_default();
+        if allowed
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(host))
+        {
+            return Ok(());
+        }
+        Err(Error::UntrustedHost {
+            url: url.to_string(),
+            host: host.to_string(),
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder;
+
+    #[test]
+    fn check_host_allows_only_the_given_hosts() {
+        let updater = builder()
+            .repo_author("author")
+            .repo_name("repo")
+            .bin_name("tool")
+            .allowed_download_hosts(vec!["github.com".to_string()])
+            .build()
+            .unwrap();
+        let url = |url: &str| Url::parse(url).unwrap();
+
+        updater
+            .check_host(&url(
+                "https://GitHub.com/author/repo/releases/download/v1/tool",
+            ))
+            .unwrap();
+        let untrusted = updater
+            .check_host(&url("https://example.com/tool"))
+            .unwrap_err();
+        assert!(matches!(
+            untrusted.downcast_ref(),
+            Some(Error::UntrustedHost { host, .. }) if host == "example.com"
+        ));
+    }
+}
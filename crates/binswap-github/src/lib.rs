@@ -58,42 +58,115 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! # Proxies
+//!
+//! Requests go through the proxies given by the standard environment
+//! variables: `HTTPS_PROXY` for HTTPS URLs, `HTTP_PROXY` for HTTP URLs, or
+//! their lowercase forms if the uppercase ones are not set. Hosts listed in
+//! `NO_PROXY` (or `no_proxy`), such as `internal.example.com,10.0.0.0/8`, are
+//! reached directly. On Windows, the system proxy settings are used if none
+//! of the variables are set. `HTTP_PROXY` is ignored when running as a CGI
+//! script, since it can be set by a request header there.
+//!
+//! The environment is read whenever a client is built, which is once per
+//! operation unless a [`client`](BinswapGithubBuilder::client) is given, in
+//! which case that client is used as is.
 
 #![warn(missing_docs)]
 
+mod archive;
+mod asset;
+mod batch;
+mod binstall;
+#[cfg(feature = "blocking")]
+mod blocking;
+mod cache;
+mod check;
+mod checksum;
+mod companion;
+mod distro;
+mod error;
+mod events;
+mod forge;
+mod graphql;
+mod health;
+mod hook;
+mod host;
+mod hosts;
+mod linkage;
+mod local;
+mod lock;
+mod metrics;
+mod minisign;
+mod netrc;
+mod outcome;
+mod probe;
+mod progress;
+mod recover;
+mod release;
+mod repo;
+mod report;
+mod retry;
+mod secret;
+mod sidecar;
+mod tag;
+mod user_path;
+mod verify;
+
 use std::{
     borrow::Cow,
+    collections::HashMap,
     env,
-    io::{self, stderr, BufRead, StdinLock},
-    num::NonZeroU64,
-    path::Path,
-    sync::Arc,
+    ffi::OsString,
+    io::{self, BufRead, StdinLock},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
 
-use binstalk::{
-    fetchers::{Data, Fetcher, GhCrateMeta, TargetData},
-    get_desired_targets,
-    helpers::{
-        download::ExtractedFilesEntry,
-        gh_api_client::GhApiClient,
-        remote::{Client, Url},
-    },
-    manifests::cargo_toml_binstall::PkgMeta,
+use binstalk::helpers::{
+    download::{ExtractedFiles, ExtractedFilesEntry},
+    remote::Url,
 };
 use color_eyre::{
     eyre::{eyre, Context},
     Result,
 };
-use crossterm::{
-    cursor::{RestorePosition, SavePosition},
-    style::{Print, ResetColor, Stylize},
-    ExecutableCommand,
-};
 use derive_builder::Builder;
-use serde::Deserialize;
-use tokio::sync::oneshot;
+use futures_util::{future::BoxFuture, pin_mut, stream, StreamExt};
+use sha2::{Digest, Sha256};
+use tokio::sync::{mpsc, oneshot};
+
+use hook::Hook;
+use lock::SwapLock;
+use metrics::extracted_size;
+use probe::Probe;
+use report::{ExecutableCommand, Print, ResetColor, RestorePosition, SavePosition, Stylize};
+use secret::Secret;
+use verify::guess_version;
+
+pub use batch::{install_batch, PlannedUpdate};
+pub use binstalk::helpers::remote::Client;
+pub use binstalk::manifests::cargo_toml_binstall::PkgFmt;
+pub use cache::VersionCache;
+pub use check::{CheckArgs, CheckExpectation, SandboxSpec};
+pub use checksum::ChecksumKind;
+pub use companion::CompanionAsset;
+pub use distro::DistroPackage;
+pub use error::Error;
+pub use events::{ConfirmContext, ConfirmRequest, ConfirmStrength, SwapEvent};
+pub use forge::ForgeKind;
+pub use metrics::UpdateMetrics;
+pub use outcome::{InstallAllOutcome, SkipReason, SwapOutcome, SwapStatus};
+pub use probe::ResolvedAsset;
+pub use progress::ProgressEvent;
+pub use release::{AssetInfo, ReleaseInfo, Tiebreak};
+pub use repo::RepoId;
+pub use report::Reporter;
+pub use sidecar::{SidecarFile, VerificationFiles};
+pub use verify::VerifyResult;
 
 /// Create a new builder. Finish by calling `.build()`
 pub fn builder() -> BinswapGithubBuilder {
@@ -113,32 +186,469 @@ pub struct BinswapGithub {
     /// used.
     #[builder(setter(into, strip_option), default)]
     asset_name: Option<String>,
+    /// The path of the binary within the asset, relative to its root, such
+    /// as `{ name }-{ target }/bin/{ bin }{ binary-ext }`. The `{ name }`,
+    /// `{ version }`, `{ target }`, `{ bin }` and `{ binary-ext }` tokens are
+    /// replaced as by `cargo-binstall`. This takes precedence over the
+    /// `bin-dir` of the `cargo-binstall` metadata. If neither is given, the
+    /// binary is looked for in the root of the asset and its directories.
+    #[builder(setter(into, strip_option), default)]
+    bin_dir: Option<String>,
+    /// How many directories deep the binary is looked for in the asset, if
+    /// no `bin_dir` is given. The root of the asset is always searched.
+    #[builder(setter(into), default = "4")]
+    max_search_depth: usize,
     /// The name of the binary in the release.
     #[builder(setter(into))]
     bin_name: String,
+    /// The file name to install the binary as with
+    /// [`fetch_and_install_into`](BinswapGithub::fetch_and_install_into),
+    /// such as to install it side-by-side with another version. If not
+    /// given `bin_name` will be used.
+    #[builder(setter(into, strip_option), default)]
+    install_as: Option<String>,
+    /// The forge the repository is hosted on.
+    #[builder(default)]
+    forge: ForgeKind,
+    /// The base URL of the API of the forge, including any path prefix, such
+    /// as `https://github.example.com/api/v3` for GitHub Enterprise Server.
+    /// Defaults to `https://api.github.com`, `https://gitlab.com/api/v4` on
+    /// GitLab, or `https://codeberg.org/api/v1` on Gitea.
+    #[builder(setter(into, strip_option), default)]
+    api_base_url: Option<String>,
+    /// The base URL of repositories on the forge, which release assets are
+    /// downloaded from, such as `https://github.example.com`. Defaults to
+    /// `https://github.com`, `https://gitlab.com` on GitLab, or
+    /// `https://codeberg.org` on Gitea.
+    #[builder(setter(into, strip_option), default)]
+    download_base_url: Option<String>,
+    /// The token used to authenticate with the forge. If not given,
+    /// `GH_TOKEN` or `GITHUB_TOKEN` from the environment is used, or
+    /// `GITLAB_TOKEN` on GitLab, or `GITEA_TOKEN` on Gitea.
+    #[builder(setter(custom), default)]
+    github_token: Option<Secret>,
+    /// Fall back to the password of the entry for the host of
+    /// `api_base_url` or `download_base_url` in the `.netrc` file, or of its
+    /// `default` entry, as the token, if no token is given otherwise. The
+    /// file is read from `NETRC` if set, or from the home directory.
+    #[builder(setter(into), default = "false")]
+    use_netrc: bool,
+    /// How long a request to GitHub may take, including downloading the
+    /// response, such as a release asset. Consider raising this when
+    /// downloading large binaries over slow networks.
+    #[builder(setter(into), default = "Duration::from_secs(30)")]
+    request_timeout: Duration,
+    /// The client to make requests with, such as one configured with custom
+    /// TLS roots, and shared with the rest of an application. If not given, a
+    /// client is built for every operation, using the proxies from the
+    /// environment, see [Proxies](crate#proxies).
+    #[builder(setter(into, strip_option), default)]
+    client: Option<Client>,
+    /// Look up the latest release with the GraphQL API of GitHub, which
+    /// requires a token. Without a token, or if the request fails, the REST
+    /// API is used.
+    #[builder(setter(into), default = "false")]
+    use_graphql: bool,
     /// The desired version to download. If not given the latest will be used.
+    ///
+    /// This can also be a semantic version range, like `^1.2` or
+    /// `>=1.0, <2.0`, in which case the release with the highest matching
+    /// version is used. Ranges are recognized by their operators, so `1.2`
+    /// is still looked up as the exact tag, and releases whose versions
+    /// only differ in build metadata are chosen between by `tiebreak_by`.
     #[builder(setter(into, strip_option), default)]
     version: Option<String>,
+    /// How to choose between releases whose versions have the same
+    /// precedence, such as `1.2.3+build.5` and `1.2.3+build.6`, when looking
+    /// for the highest version matching a range or with
+    /// [`latest_across`](BinswapGithub::latest_across). Defaults to the most
+    /// recently published.
+    #[builder(default)]
+    tiebreak_by: Tiebreak,
+    /// Follow a tag which is moved to new builds, such as `nightly`, rather
+    /// than a versioned release. This takes precedence over `version`.
+    ///
+    /// Whether a new build is available is determined by comparing the
+    /// [`build_id`](SwapOutcome::build_id) of the release to
+    /// `installed_build`, since the version never changes.
+    #[builder(setter(into, strip_option), default)]
+    rolling_tag: Option<String>,
+    /// The [`build_id`](SwapOutcome::build_id) of the currently installed
+    /// build of the `rolling_tag`. If it matches the release, the
+    /// installation is skipped.
+    #[builder(setter(into, strip_option), default)]
+    installed_build: Option<String>,
+    /// After moving the old binary aside, check that the moved file has the
+    /// same size and SHA-256 digest as before, moving it back and aborting
+    /// the update otherwise. This guards against the filesystem misbehaving
+    /// while the binary is being replaced.
+    #[builder(setter(into), default = "false")]
+    verify_backup: bool,
+    /// Keep a copy of the replaced binary at this path after a successful
+    /// swap, such that the update can be rolled back manually. An existing
+    /// file at the path is overwritten. If it is a directory, the binary is
+    /// kept in it under its file name, which keeps each of the binaries when
+    /// several are installed. Nothing is written if there was no binary to
+    /// replace, or if the swap is rolled back.
+    #[builder(setter(into, strip_option), default)]
+    keep_backup: Option<PathBuf>,
+    /// Do not print any status messages to stderr, leaving all output to the
+    /// caller. The prompt for confirmation is still shown, unless
+    /// `no_confirm` or `confirm_with` is set.
+    #[builder(setter(into), default = "false")]
+    quiet: bool,
+    /// Print every [`SwapEvent`] to stdout as a line of JSON, such that the
+    /// progress can be followed by log aggregators. The objects have an
+    /// `event` field naming the kind of event, such as `version_resolved`,
+    /// and end with a `done` event holding the outcome. This is independent
+    /// of the status messages printed to stderr.
+    #[builder(setter(into), default = "false")]
+    json_events: bool,
     /// Do not prompt user for confirmation before installing.
     #[builder(setter(into), default = "false")]
     no_confirm: bool,
-    /// The command to run to check that the binary is executable before
-    /// installing it.
-    #[builder(setter(into), default = "\"--help\".to_string()")]
-    check_with_cmd: String,
+    /// Proceed without confirmation if the user cannot be prompted since stdin
+    /// is not a terminal, such as in CI. Otherwise this fails with
+    /// [`Error::NotInteractive`], unless `no_confirm` is set.
+    #[builder(setter(into), default = "false")]
+    assume_yes_when_noninteractive: bool,
+    /// How strongly the user has to confirm replacing the currently executed
+    /// binary with
+    /// [`fetch_and_write_in_place_of_current_exec`](BinswapGithub::fetch_and_write_in_place_of_current_exec),
+    /// since it is not reversible. Other installations are confirmed with
+    /// `yes` or `no`.
+    #[builder(default)]
+    confirm_strength: ConfirmStrength,
+    /// The arguments to run the binary with to check that it is executable
+    /// before installing it, either as a string split on whitespace or as a
+    /// list of arguments.
+    #[builder(setter(into), default = "\"--help\".into()")]
+    check_with_cmd: CheckArgs,
     /// Do not run the check command before installing.
     #[builder(setter(into), default = "false")]
     no_check_with_cmd: bool,
+    /// How long the check command may run before it is killed and the check
+    /// fails with [`Error::CheckTimedOut`], such that a binary waiting for
+    /// input does not hang the update.
+    #[builder(setter(into), default = "Duration::from_secs(10)")]
+    check_timeout: Duration,
+    /// What the check command has to produce for the check to pass. By
+    /// default it only has to exit successfully.
+    #[builder(setter(into, strip_option), default)]
+    check_expect: Option<CheckExpectation>,
+    /// Environment variables to set for the check command.
+    #[builder(setter(custom), default)]
+    check_env: Vec<(String, String)>,
+    /// Run the check command with only the variables of `check_env`, rather
+    /// than those of the current process, such that the check does not
+    /// depend on the environment it is run in.
+    #[builder(setter(into), default = "false")]
+    check_clear_env: bool,
+    /// Run the installed binary with `smoke_test_args` after swapping it in.
+    /// If that fails, the previous binary is restored and the binary of the
+    /// next candidate target is tried instead, which recovers from picking a
+    /// binary for the wrong libc. The binary is run like the check command,
+    /// with the `check_env` and for at most `check_timeout`.
+    #[builder(setter(into), default = "false")]
+    post_install_smoke_test: bool,
+    /// The arguments to run the installed binary with for
+    /// `post_install_smoke_test`.
+    #[builder(setter(into), default = "vec![\"--version\".to_string()]")]
+    smoke_test_args: Vec<String>,
+    /// Require the downloaded binary to be linked statically, failing with
+    /// [`Error::NotStaticallyLinked`] otherwise. ELF binaries must not
+    /// request a dynamic loader, Mach-O binaries must not link against
+    /// libraries other than `libSystem`, and Windows binaries must not link
+    /// against the C or C++ runtime dynamically.
+    #[builder(setter(into), default = "false")]
+    require_static: bool,
+    /// Verify the downloaded asset against the SHA-256 checksum published in
+    /// the release, failing with [`Error::ChecksumMismatch`] before it is
+    /// extracted if it differs. A missing checksum is also an error.
+    #[builder(setter(into, strip_option), default)]
+    verify_checksum: Option<ChecksumKind>,
+    /// The minisign public key to verify the downloaded asset with, either
+    /// the contents of the `.pub` file or just its key line. The signature is
+    /// taken from the `.minisig` or `.sig` file next to the asset in the
+    /// release. Targets whose asset has no signature are skipped in favor
+    /// of signed ones, failing before anything is downloaded if none is
+    /// signed. A missing or bad signature fails with
+    /// [`Error::BadSignature`], and nothing is installed.
+    #[builder(setter(into, strip_option), default)]
+    pub_key: Option<String>,
+    /// A glob pattern, such as `*/bin/tool`, which the path or file name of
+    /// an entry in the downloaded archive must match, failing with
+    /// [`Error::ExpectedEntryMissing`] otherwise. The entries are checked
+    /// once the archive has been extracted, before anything is installed.
+    #[builder(setter(into, strip_option), default)]
+    require_archive_entry: Option<String>,
+    /// The media type the release asset must have, such as
+    /// `application/gzip`. It is compared to the `content_type` of the asset
+    /// in the release before downloading it, and a mismatch fails with
+    /// [`Error::UnexpectedAssetContentType`]. Assets which cannot be
+    /// identified among the assets of the release are not checked.
+    #[builder(setter(into, strip_option), default)]
+    expected_content_type: Option<String>,
+    /// The bounds of the size of the downloaded binary in bytes, see
+    /// [`expected_size_range`](BinswapGithubBuilder::expected_size_range).
+    #[builder(setter(custom), default)]
+    expected_size_range: (Option<u64>, Option<u64>),
+    /// Run the check command inside of a sandbox.
+    #[builder(setter(into, strip_option), default)]
+    check_sandbox: Option<SandboxSpec>,
+    /// Fail with [`Error::SandboxUnavailable`] if `check_sandbox` is set, but
+    /// the sandbox is not available. If disabled, the check is skipped
+    /// instead.
+    #[builder(setter(into), default = "true")]
+    check_sandbox_strict: bool,
     /// Determine and download binary, but do not install it.
     #[builder(setter(into), default = "false")]
     dry_run: bool,
+    /// The path of the currently executed binary, used by
+    /// [`fetch_and_write_in_place_of_current_exec`](BinswapGithub::fetch_and_write_in_place_of_current_exec).
+    /// If not given, [`std::env::current_exe`] is used, which can fail or be
+    /// misleading in some environments.
+    #[builder(setter(into, strip_option), default)]
+    current_exe_override: Option<PathBuf>,
+    /// Add the directory to the `PATH` of the user after installing into it
+    /// with [`fetch_and_install_into`](BinswapGithub::fetch_and_install_into)
+    /// or [`fetch_and_install_all_into`](BinswapGithub::fetch_and_install_all_into),
+    /// so the installed binaries can be run. This only has an effect on
+    /// Windows, where the directory is added in the registry.
+    #[builder(setter(into), default = "false")]
+    add_to_user_path: bool,
+    /// Keep the previous binary after swapping, until the new one calls
+    /// [`confirm_healthy`](BinswapGithub::confirm_healthy). If the next
+    /// update finds that the update was never confirmed, the previous binary
+    /// is restored first, and the version which was rolled back is skipped.
+    #[builder(setter(into), default = "false")]
+    provisional: bool,
+    /// Do not lock the target while swapping it. By default the target is
+    /// locked across processes, so concurrent updates of the same binary do
+    /// not overwrite each other. An update which waited for another one is
+    /// skipped as up to date if that one installed the same binary.
+    #[builder(setter(into), default = "false")]
+    no_lock: bool,
+    /// How long to wait for another process updating the same target before
+    /// failing with [`Error::LockTimeout`].
+    #[builder(setter(into), default = "Duration::from_secs(60)")]
+    lock_timeout: Duration,
+    /// Create the directory the binary is written to, and any missing parent
+    /// directories, if it does not exist.
+    #[builder(setter(into), default = "false")]
+    create_dirs: bool,
+    /// The directory to download and extract assets in, rather than the
+    /// system temporary directory. Placing it beside the installed binary
+    /// keeps the final rename on the same filesystem, and avoids a small or
+    /// `noexec` `/tmp`, in which the check command cannot run.
+    #[builder(setter(into, strip_option), default)]
+    temp_dir: Option<PathBuf>,
+    /// Skip the installation if the downloaded binary is byte-for-byte
+    /// identical to the one already at the target path.
+    #[builder(setter(into), default = "false")]
+    skip_if_identical: bool,
+    /// Require the git tag of the release to be signed, and the signature
+    /// verified by GitHub.
+    #[builder(setter(into), default = "false")]
+    require_verified_tag: bool,
+    /// The emails of the taggers allowed to sign the release tag, when
+    /// `require_verified_tag` is set. If not given, any verified signature is
+    /// accepted.
+    #[builder(setter(into, strip_option), default)]
+    allowed_tag_signers: Option<Vec<String>>,
+    /// The identity and OIDC issuer of the cosign bundle to verify the asset
+    /// with, see [`cosign_verify`](BinswapGithubBuilder::cosign_verify).
+    #[builder(setter(custom), default)]
+    cosign_verify: Option<(String, String)>,
+    /// Refuse to install a release marked as a pre-release, failing with
+    /// [`Error::PrereleaseNotAllowed`]. This guards against a pre-release
+    /// being given as `version` by mistake.
+    #[builder(setter(into), default = "false")]
+    forbid_prerelease: bool,
+    /// Install pre-releases even if `forbid_prerelease` is set. When no
+    /// `version` is given, the newest release is used even if it is a
+    /// pre-release, rather than the latest release as defined by GitHub,
+    /// which never is one.
+    #[builder(setter(into), default = "false")]
+    allow_prerelease: bool,
+    /// When no `version` is given, use the newest release even if it is a
+    /// draft. Drafts are only visible with a token with push access to the
+    /// repository.
+    #[builder(setter(into), default = "false")]
+    allow_draft: bool,
+    /// A cache of the latest releases, shared with other builders, which is
+    /// used rather than looking up the latest release again. This avoids
+    /// hitting the rate limit when checking for updates often.
+    #[builder(setter(into, strip_option), default)]
+    version_cache: Option<Arc<VersionCache>>,
+    /// HTTP status codes to retry requests on, in addition to the server
+    /// errors (`5xx`) and `429 Too Many Requests`.
+    #[builder(setter(into), default)]
+    retryable_statuses: Vec<u16>,
+    /// How many times a request to GitHub, or the download of an asset, is
+    /// retried after failing transiently, such as by timing out, losing the
+    /// connection, or with a `5xx` or `429 Too Many Requests` status.
+    #[builder(setter(into), default = "3")]
+    max_retries: u8,
+    /// The delay before the first retry, which is doubled for every retry.
+    /// Up to half of the delay is added at random, such that clients failing
+    /// at the same time spread out their retries.
+    #[builder(setter(into), default = "Duration::from_millis(500)")]
+    retry_backoff: Duration,
+    /// The combined time which may be spent retrying requests during an
+    /// update, across looking up the release and downloading it. Once it is
+    /// used up, failing requests are not retried even if retries remain.
+    /// Every call, such as to [`list_versions`](BinswapGithub::list_versions),
+    /// has a budget of its own.
+    #[builder(setter(into, strip_option), default)]
+    total_retry_budget: Option<Duration>,
+    /// Only install the release if this returns `true` for it, see
+    /// [`install_if`](BinswapGithubBuilder::install_if).
+    #[builder(setter(custom), default)]
+    install_if: Option<Hook<InstallIf>>,
+    /// The version of the binary being replaced. If the resolved version is
+    /// the same, compared as semantic versions if possible, the update is
+    /// skipped as [`UpToDate`](SkipReason::UpToDate) before anything is
+    /// downloaded.
+    #[builder(setter(into, strip_option), default)]
+    current_version: Option<String>,
+    /// Determines `current_version` from the binary being replaced, see
+    /// [`detect_current_version`](BinswapGithubBuilder::detect_current_version).
+    #[builder(setter(custom), default)]
+    detect_current_version: Option<Hook<DetectVersion>>,
+    /// Install the resolved version even if it is older than the current
+    /// version, rather than failing with [`Error::Downgrade`]. Without a
+    /// known current version this has no effect.
+    #[builder(setter(into), default = "false")]
+    allow_downgrade: bool,
+    /// Only install the release if it was published at least this long ago.
+    /// This avoids installing fresh releases which might still be yanked.
+    #[builder(setter(into, strip_option), default)]
+    min_release_age: Option<Duration>,
+    /// The path of a `Cargo.toml` in the repository, such as `Cargo.toml`, whose
+    /// `[package.metadata.binstall]` section describes the release assets.
+    /// It is read from the tagged commit of the release, the same way
+    /// `cargo-binstall` would use it.
+    #[builder(setter(into, strip_option), default)]
+    binstall_manifest: Option<String>,
+    /// Additional assets of the release to install after the binary, such
+    /// as shell completions or man pages.
+    #[builder(setter(into), default)]
+    companion_assets: Vec<CompanionAsset>,
+    /// Take the binary out of a `.deb` or `.rpm` package in the release,
+    /// rather than an archive. The package is matched by its file
+    /// extension, the architecture of the target and `asset_name`, and the
+    /// binary is looked for in the usual directories such as `/usr/bin`.
+    #[builder(setter(into, strip_option), default)]
+    distro_package: Option<DistroPackage>,
+    /// Substrings the file name of the asset must contain.
+    #[builder(setter(custom), default)]
+    asset_must_contain: Vec<String>,
+    /// Substrings the file name of the asset must not contain.
+    #[builder(setter(custom), default)]
+    asset_must_not_contain: Vec<String>,
+    /// The formats of assets with the given extensions, such as `.tgz`,
+    /// which are used in place of guessing it. The empty extension applies
+    /// to assets without a recognized extension. An asset is chosen by its
+    /// extension if its file name contains `asset_name` and the target.
+    #[builder(setter(into), default)]
+    extension_format_map: HashMap<String, PkgFmt>,
+    /// The format of the asset, used in place of guessing it from its file
+    /// name, for assets with unusual extensions. The formats supported by
+    /// binstalk are `tar`, `tbz2`, `tgz`, `txz`, `tzstd`, `zip` and `bin`,
+    /// the latter being the binary itself.
+    #[builder(setter(into, strip_option), default)]
+    pkg_fmt: Option<PkgFmt>,
+    /// A template of the URL of the asset, tried before the one in the
+    /// binstall metadata and the default templates. The `{ repo }`,
+    /// `{ name }`, `{ version }`, `{ target }`, `{ archive-format }`,
+    /// `{ archive-suffix }` and `{ binary-ext }` tokens are replaced as by
+    /// `cargo-binstall`, where `{ repo }` is the URL of the repository.
+    #[builder(setter(into, strip_option), default)]
+    pkg_url: Option<String>,
+    /// The maximum number of candidate targets to look for an asset for,
+    /// after which [`Error::AssetNotFound`] is returned. All of them are
+    /// tried if not given.
+    #[builder(setter(into, strip_option), default)]
+    max_targets_to_try: Option<usize>,
+    /// How many candidate targets are looked for an asset for at once. The
+    /// asset of the earliest target is still the one installed.
+    #[builder(setter(into), default = "4")]
+    probe_concurrency: usize,
+    /// The hosts assets may be downloaded from, such as `github.com` and
+    /// `objects.githubusercontent.com`. The URL of the asset and the URL its
+    /// download is finally served from, after any redirects, are both
+    /// checked, failing with [`Error::UntrustedHost`] before anything is read
+    /// from it if either is on another host. Any host is allowed if not
+    /// given.
+    #[builder(setter(into, strip_option), default)]
+    allowed_download_hosts: Option<Vec<String>>,
     /// The possible targets to download. If provided, targets will not be
     /// auto-detected.
     #[builder(setter(into, strip_option), default)]
     targets: Option<Vec<String>>,
+    /// Whether the host uses the hard-float ABI, overriding the detection.
+    /// On 32-bit ARM this decides which of the detected targets are tried
+    /// first, and hard-float targets are not tried on soft-float hosts.
+    #[builder(setter(into, strip_option), default)]
+    host_hard_float: Option<bool>,
+    /// The memory page size of the host in bytes, overriding the detection.
+    /// Binaries which cannot be loaded with this page size are skipped in
+    /// favor of the next target.
+    #[builder(setter(into, strip_option), default)]
+    host_page_size: Option<u64>,
+    /// The version of macOS on the host, such as `13.5`, overriding the
+    /// detection. Mach-O binaries requiring a newer version are skipped in
+    /// favor of the next target.
+    #[builder(setter(into, strip_option), default)]
+    host_macos_version: Option<String>,
+    /// A glob matched against the file names in the release asset, selecting
+    /// the binaries installed by
+    /// [`fetch_and_install_all_into`](BinswapGithub::fetch_and_install_all_into).
+    /// On Windows the `.exe` extension may be left out of the glob.
+    #[builder(setter(into, strip_option), default)]
+    bin_glob: Option<String>,
+    /// Asked whether to proceed with an installation, see
+    /// [`confirm_with`](BinswapGithubBuilder::confirm_with).
+    #[builder(setter(custom), default)]
+    confirm_with: Option<Hook<ConfirmWith>>,
+    /// Called right before the binary is swapped, see
+    /// [`on_before_swap`](BinswapGithubBuilder::on_before_swap).
+    #[builder(setter(custom), default)]
+    on_before_swap: Option<Hook<BeforeSwap>>,
+    /// Called with the metrics of every update once it has finished, see
+    /// [`on_metrics`](BinswapGithubBuilder::on_metrics).
+    #[builder(setter(custom), default)]
+    on_metrics: Option<Hook<OnMetrics>>,
+    /// Called with the progress of updates, see
+    /// [`on_progress`](BinswapGithubBuilder::on_progress).
+    #[builder(setter(custom), default)]
+    on_progress: Option<Hook<OnProgress>>,
+    /// Receives the status messages, see
+    /// [`reporter`](BinswapGithubBuilder::reporter).
+    #[builder(setter(custom), default)]
+    reporter: Option<Hook<dyn Reporter>>,
+    #[builder(setter(skip), default)]
+    event_sender: Option<mpsc::UnboundedSender<SwapEvent>>,
+    #[builder(setter(skip), default)]
+    metrics: Option<Arc<Mutex<UpdateMetrics>>>,
+    #[builder(setter(skip), default)]
+    in_place: bool,
+    #[builder(setter(skip), default)]
+    retry_spent: Arc<Mutex<Duration>>,
 }
 
 impl BinswapGithubBuilder {
+    /// The token used to authenticate with GitHub. If not given, `GH_TOKEN`
+    /// or `GITHUB_TOKEN` from the environment is used.
+    ///
+    /// The token is sent as a bearer token, and is left out of the `Debug`
+    /// output.
+    pub fn github_token(&mut self, token: impl Into<String>) -> &mut Self {
+        self.github_token = Some(Some(Secret(token.into())));
+        self
+    }
+
     /// Add the target to list of possible targets to download. If provided,
     /// targets will not be auto-detected.
     pub fn add_target(&mut self, target: impl Into<String>) -> &mut Self {
@@ -149,6 +659,283 @@ impl BinswapGithubBuilder {
             .push(target.into());
         self
     }
+
+    /// Require the file name of the asset to contain `part`, such as `musl`.
+    /// The asset is then chosen among those whose file names contain
+    /// `asset_name`, the target and all such parts, and none of the parts
+    /// given to [`asset_must_not_contain`](Self::asset_must_not_contain).
+    /// Targets without such an asset are skipped, and several such assets
+    /// fail with [`Error::AmbiguousAsset`].
+    pub fn asset_must_contain(&mut self, part: impl Into<String>) -> &mut Self {
+        self.asset_must_contain
+            .get_or_insert_with(Vec::new)
+            .push(part.into());
+        self
+    }
+
+    /// Require the file name of the asset to not contain `part`, such as
+    /// `debug`, see [`asset_must_contain`](Self::asset_must_contain).
+    pub fn asset_must_not_contain(&mut self, part: impl Into<String>) -> &mut Self {
+        self.asset_must_not_contain
+            .get_or_insert_with(Vec::new)
+            .push(part.into());
+        self
+    }
+
+    /// Add an asset to install alongside the binary, see
+    /// [`CompanionAsset`].
+    pub fn add_companion_asset(&mut self, companion: CompanionAsset) -> &mut Self {
+        self.companion_assets
+            .get_or_insert_with(Vec::new)
+            .push(companion);
+        self
+    }
+
+    /// Set the environment variable `key` to `value` for the check command,
+    /// such as `NO_COLOR=1` or the path of a configuration file. This can be
+    /// called several times to set several variables.
+    pub fn check_env(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.check_env
+            .get_or_insert_with(Vec::new)
+            .push((key.into(), value.into()));
+        self
+    }
+
+    /// Only install the release if `predicate` returns `true` for it. It is
+    /// checked after the version is resolved, and before anything is
+    /// downloaded. If it returns `false`, the installation is skipped.
+    pub fn install_if(
+        &mut self,
+        predicate: impl Fn(&ReleaseInfo) -> bool + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.install_if = Some(Some(Hook(Arc::new(predicate))));
+        self
+    }
+
+    /// Call `detect` with the path of the binary being replaced, such as the
+    /// current executable, to determine its version if `current_version` is
+    /// not given. This could run the binary with `--version`. It should
+    /// return `None` if the version could not be determined, in which case
+    /// the update is not skipped.
+    pub fn detect_current_version(
+        &mut self,
+        detect: impl Fn(&Path) -> Option<String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.detect_current_version = Some(Some(Hook(Arc::new(detect))));
+        self
+    }
+
+    /// Call `hook` to confirm an installation instead of prompting the user
+    /// on stdin, such as to ask through a graphical interface. It is given
+    /// the path and version about to be installed, and the installation is
+    /// [`Declined`](SwapStatus::Declined) if it returns `false`. It is not
+    /// called if `no_confirm` is set.
+    pub fn confirm_with(
+        &mut self,
+        hook: impl for<'a> Fn(&'a ConfirmContext) -> BoxFuture<'a, bool> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.confirm_with = Some(Some(Hook(Arc::new(hook))));
+        self
+    }
+
+    /// Call `hook` right before the binary is written to the target by
+    /// [`fetch_and_write_to`](BinswapGithub::fetch_and_write_to), after it
+    /// has been checked and confirmed, with the outcome the update will
+    /// have. If it returns `false`, the target is left untouched and the
+    /// update is [`Declined`](SwapStatus::Declined).
+    ///
+    /// This allows preparing for the irreversible swap, such as by taking a
+    /// snapshot of the file system. It is not called on dry-runs.
+    pub fn on_before_swap(
+        &mut self,
+        hook: impl for<'a> Fn(&'a SwapOutcome) -> BoxFuture<'a, bool> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_before_swap = Some(Some(Hook(Arc::new(hook))));
+        self
+    }
+
+    /// Call `hook` with the [`UpdateMetrics`] of every update once it has
+    /// finished, whether it succeeded or not. This allows reporting the
+    /// behavior of updates to a metrics system.
+    pub fn on_metrics(
+        &mut self,
+        hook: impl Fn(UpdateMetrics) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_metrics = Some(Some(Hook(Arc::new(hook))));
+        self
+    }
+
+    /// Call `hook` with the [`ProgressEvent`]s of updates as they happen, such
+    /// that a graphical frontend can show them. The status messages printed
+    /// to stderr are muted while a hook is set. Without the `terminal-output`
+    /// feature they are reported to it as [`ProgressEvent::Status`] instead.
+    pub fn on_progress(
+        &mut self,
+        hook: impl Fn(ProgressEvent) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_progress = Some(Some(Hook(Arc::new(hook))));
+        self
+    }
+
+    /// Send the status messages to `reporter` rather than printing them to
+    /// stderr, one line at a time and without styling. This works with or
+    /// without the `terminal-output` feature, and takes precedence over
+    /// reporting them to `on_progress`. Nothing is reported in `quiet` mode.
+    pub fn reporter(&mut self, reporter: impl Reporter + 'static) -> &mut Self {
+        self.reporter = Some(Some(Hook(Arc::new(reporter))));
+        self
+    }
+
+    /// Require the size of the downloaded binary in bytes to be within `min`
+    /// and `max`, both inclusive, failing with
+    /// [`Error::UnexpectedBinarySize`] otherwise. Either bound can be `None`.
+    pub fn expected_size_range(
+        &mut self,
+        min: impl Into<Option<u64>>,
+        max: impl Into<Option<u64>>,
+    ) -> &mut Self {
+        self.expected_size_range = Some((min.into(), max.into()));
+        self
+    }
+
+    /// Verify the downloaded asset against the Sigstore bundle published
+    /// next to it, signed keyless with cosign by `identity` as issued by the
+    /// OIDC `issuer`.
+    ///
+    /// This is not supported yet, and every update fails with
+    /// [`Error::Unsupported`] before anything is downloaded. Verifying a
+    /// keyless bundle means validating the Fulcio certificate chain against
+    /// the Sigstore trust root and checking the Rekor inclusion proof, which
+    /// needs an implementation of the trust root the crate does not have,
+    /// and checking only the identity would give a false sense of security.
+    /// The option exists so that a configuration asking for the
+    /// verification never installs an unverified binary.
+    pub fn cosign_verify(
+        &mut self,
+        identity: impl Into<String>,
+        issuer: impl Into<String>,
+    ) -> &mut Self {
+        self.cosign_verify = Some(Some((identity.into(), issuer.into())));
+        self
+    }
+
+    /// Set the repository and version from the URL of a GitHub release, like
+    /// `https://github.com/BurntSushi/ripgrep/releases/tag/14.1.0`. The URL of
+    /// a release asset, like `.../releases/download/14.1.0/<asset>`, also
+    /// sets `asset_name` to the part of the file name before the version, if
+    /// the version is found in it.
+    ///
+    /// Fails with [`Error::UnrecognizedReleaseUrl`] for other URLs.
+    ///
+    /// ```
+    /// # fn main() -> color_eyre::Result<()> {
+    /// let binswap = binswap_github::builder()
+    ///     .from_release_url("https://github.com/BurntSushi/ripgrep/releases/tag/14.1.0")?
+    ///     .bin_name("rg")
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_release_url(&mut self, url: impl AsRef<str>) -> Result<&mut Self> {
+        let url = url.as_ref();
+        let unrecognized = || Error::UnrecognizedReleaseUrl {
+            url: url.to_string(),
+        };
+
+        let parsed = Url::parse(url).map_err(|_| unrecognized())?;
+        if !matches!(parsed.host_str(), Some("github.com" | "www.github.com")) {
+            return Err(unrecognized().into());
+        }
+        let segments: Vec<&str> = parsed
+            .path_segments()
+            .ok_or_else(unrecognized)?
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let (author, name, tag, asset) = match segments.as_slice() {
+            [author, name, "releases", "tag", tag] => (author, name, tag, None),
+            [author, name, "releases", "download", tag, asset] => (author, name, tag, Some(asset)),
+            _ => return Err(unrecognized().into()),
+        };
+
+        let version = tag.trim_start_matches('v');
+        self.repo_author(*author)
+            .repo_name(*name)
+            .version(version.to_string());
+        if let Some(asset_name) = asset.and_then(|asset| asset_name_before(asset, version)) {
+            self.asset_name(asset_name);
+        }
+        Ok(self)
+    }
+
+    /// Override the configuration with `BINSWAP_*` environment variables, if
+    /// they are set. This allows operators to change the behavior of a
+    /// deployed binary without recompiling it.
+    ///
+    /// | Variable               | Setter                                               |
+    /// |------------------------|------------------------------------------------------|
+    /// | `BINSWAP_GITHUB_TOKEN` | [`github_token`](Self::github_token)                 |
+    /// | `BINSWAP_MIRROR_URL`   | [`download_base_url`](Self::download_base_url)       |
+    /// | `BINSWAP_API_URL`      | [`api_base_url`](Self::api_base_url)                 |
+    /// | `BINSWAP_VERSION`      | [`version`](Self::version)                           |
+    /// | `BINSWAP_TARGETS`      | [`targets`](Self::targets), separated by commas      |
+    /// | `BINSWAP_DRY_RUN`      | [`dry_run`](Self::dry_run)                           |
+    /// | `BINSWAP_NO_CONFIRM`   | [`no_confirm`](Self::no_confirm)                     |
+    /// | `BINSWAP_NO_CHECK`     | [`no_check_with_cmd`](Self::no_check_with_cmd)       |
+    ///
+    /// Flags are disabled by `0`, `false`, `no` or the empty string, and
+    /// enabled by any other value.
+    pub fn apply_env_overrides(&mut self) -> &mut Self {
+        if let Ok(token) = env::var("BINSWAP_GITHUB_TOKEN") {
+            self.github_token(token);
+        }
+        if let Ok(mirror_url) = env::var("BINSWAP_MIRROR_URL") {
+            self.download_base_url(mirror_url);
+        }
+        if let Ok(api_url) = env::var("BINSWAP_API_URL") {
+            self.api_base_url(api_url);
+        }
+        if let Ok(version) = env::var("BINSWAP_VERSION") {
+            self.version(version);
+        }
+        if let Ok(targets) = env::var("BINSWAP_TARGETS") {
+            self.targets(
+                targets
+                    .split(',')
+                    .map(|target| target.trim().to_string())
+                    .filter(|target| !target.is_empty())
+                    .collect::<Vec<_>>(),
+            );
+        }
+        if let Some(dry_run) = env_flag("BINSWAP_DRY_RUN") {
+            self.dry_run(dry_run);
+        }
+        if let Some(no_confirm) = env_flag("BINSWAP_NO_CONFIRM") {
+            self.no_confirm(no_confirm);
+        }
+        if let Some(no_check) = env_flag("BINSWAP_NO_CHECK") {
+            self.no_check_with_cmd(no_check);
+        }
+        self
+    }
+}
+
+/// The part of the file name `asset` before `version`, without the
+/// separator and `v` prefix of the version.
+fn asset_name_before(asset: &str, version: &str) -> Option<String> {
+    let (name, _) = asset.split_once(version)?;
+    let name = ["-v", "_v", "-", "_"]
+        .iter()
+        .find_map(|separator| name.strip_suffix(separator))
+        .unwrap_or(name);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn env_flag(name: &str) -> Option<bool> {
+    let value = env::var(name).ok()?;
+    Some(!matches!(
+        value.trim().to_lowercase().as_str(),
+        "" | "0" | "false" | "no"
+    ))
 }
 
 impl BinswapGithub {
@@ -158,241 +945,1523 @@ impl BinswapGithub {
     /// ### Warning
     ///
     /// This action alters the binary and is **not reversible**!
-    pub async fn fetch_and_write_in_place_of_current_exec(&self) -> Result<()> {
-        self.fetch_and_write_to(std::env::current_exe()?).await
+    pub async fn fetch_and_write_in_place_of_current_exec(&self) -> Result<SwapOutcome> {
+        let mut this = self.clone();
+        this.in_place = true;
+        this.fetch_and_write_to(self.current_exe()?).await
+    }
+    /// Downloads and writes the found binary into the directory `dir`, named
+    /// after `install_as`, or `bin_name` if not given.
+    pub async fn fetch_and_install_into(&self, dir: impl AsRef<Path>) -> Result<SwapOutcome> {
+        let dir = dir.as_ref();
+        let outcome = self
+            .fetch_and_write_to(dir.join(self.installed_file_name()))
+            .await?;
+        if outcome.status == SwapStatus::Swapped {
+            self.add_dir_to_user_path(dir)?;
+        }
+        Ok(outcome)
+    }
+    /// Downloads and writes the found binary into the directory of the
+    /// currently executed binary, named `file_name`. Symlinks to the current
+    /// binary are resolved, so it is written next to the actual binary.
+    pub async fn fetch_and_install_beside_current_exe(
+        &self,
+        file_name: impl AsRef<Path>,
+    ) -> Result<SwapOutcome> {
+        let current_exe = tokio::fs::canonicalize(self.current_exe()?)
+            .await
+            .wrap_err("failed to resolve the current executable")?;
+        let dir = current_exe
+            .parent()
+            .ok_or_else(|| eyre!("the current executable has no parent directory"))?;
+        self.fetch_and_write_to(dir.join(file_name)).await
     }
-    /// Downloads and writes the found binary to the specified location.
-    pub async fn fetch_and_write_to(&self, target_binary: impl AsRef<Path>) -> Result<()> {
-        let target_binary = target_binary.as_ref();
+    /// Adds `dir` to the `PATH` of the user, if configured to and not a
+    /// dry-run.
+    fn add_dir_to_user_path(&self, dir: &Path) -> Result<()> {
+        if !self.add_to_user_path || self.dry_run {
+            return Ok(());
+        }
+        if user_path::add_to_user_path(dir)? {
+            self.stderr()
+                .execute(Print("Added ".green()))?
+                .execute(Print(format!("`{}`", dir.display())))?
+                .execute(Print(" to the user PATH\n".green()))?
+                .execute(ResetColor)?;
+        }
+        Ok(())
+    }
+    /// Like [`fetch_and_write_to`](Self::fetch_and_write_to), but always as a
+    /// dry-run, regardless of `dry_run`.
+    pub async fn fetch_and_write_to_dry(
+        &self,
+        target_binary: impl AsRef<Path>,
+    ) -> Result<SwapOutcome> {
+        self.with_dry_run(true)
+            .fetch_and_write_to(target_binary)
+            .await
+    }
+    /// Like [`fetch_and_write_to`](Self::fetch_and_write_to), but never as a
+    /// dry-run, regardless of `dry_run`.
+    pub async fn fetch_and_write_to_real(
+        &self,
+        target_binary: impl AsRef<Path>,
+    ) -> Result<SwapOutcome> {
+        self.with_dry_run(false)
+            .fetch_and_write_to(target_binary)
+            .await
+    }
+    fn with_dry_run(&self, dry_run: bool) -> BinswapGithub {
+        let mut this = self.clone();
+        this.dry_run = dry_run;
+        this
+    }
+    /// The file name of the binary when installed into a directory, which is
+    /// `install_as`, or `bin_name` if not given.
+    fn installed_file_name(&self) -> String {
+        let name = self.install_as.as_deref().unwrap_or(&self.bin_name);
+        format!("{name}{}", env::consts::EXE_SUFFIX)
+    }
+    /// The path to write the binary to when asked to write it to `path`. If
+    /// `path` is an existing directory or ends with a separator, the binary
+    /// is written into it, see
+    /// [`installed_file_name`](Self::installed_file_name).
+    fn target_binary_at(&self, path: &Path) -> PathBuf {
+        let ends_with_separator =
+            matches!(path.to_string_lossy().chars().last(), Some(c) if std::path::is_separator(c));
+        if ends_with_separator || path.is_dir() {
+            path.join(self.installed_file_name())
+        } else {
+            path.to_path_buf()
+        }
+    }
+    /// Downloads and writes the found binary to the specified location. If
+    /// it is a directory, the binary is written into it, named after
+    /// `install_as`, or `bin_name` if not given.
+    pub async fn fetch_and_write_to(&self, target_binary: impl AsRef<Path>) -> Result<SwapOutcome> {
+        let target_binary = self.target_binary_at(target_binary.as_ref());
+        let outcome = self
+            .measured(
+                |this| async move { this.write_to(&target_binary).await },
+                |outcome| (outcome.version.clone(), outcome.status.clone()),
+            )
+            .await?;
+        self.print_json_event(&SwapEvent::Done(outcome.clone()));
+        Ok(outcome)
+    }
+    async fn write_to(&self, target_binary: &Path) -> Result<SwapOutcome> {
+        self.print_updating(file_name(target_binary)?)?;
 
-        let name = target_binary
-            .file_name()
-            .ok_or_else(|| eyre!("target file had no name"))?
-            .to_str()
-            .unwrap();
+        let client = self.client()?;
+        let resolved = self.resolve(&client).await?;
 
-        let temp = tempfile::Builder::new().prefix("binswap").tempdir()?;
-
-        let client = Client::new(
-            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
-            None,
-            Duration::from_millis(5),
-            NonZeroU64::new(1).unwrap(),
-            None,
-        )?;
-
-        let gh_api_client = GhApiClient::new(
-            client.clone(),
-            env::var("GH_TOKEN")
-                .or_else(|_| env::var("GITHUB_TOKEN"))
-                .ok()
-                .map(Into::into),
-        );
+        self.write_resolved_to(&client, resolved, target_binary)
+            .await
+    }
+    /// Downloads the binary of the resolved release and writes it to
+    /// `target_binary`. If the smoke test of the installed binary fails, it
+    /// is installed again from the next candidate target.
+    async fn write_resolved_to(
+        &self,
+        client: &Client,
+        resolved: Resolved,
+        target_binary: &Path,
+    ) -> Result<SwapOutcome> {
+        if !self.post_install_smoke_test {
+            return self
+                .write_resolved_once_to(client, resolved, target_binary)
+                .await;
+        }
 
-        stderr()
-            .execute(Print("Updating ".green()))?
-            .execute(Print(&name))?
-            .execute(Print("...\n".green()))?
-            .execute(ResetColor)?;
+        let (mut targets, _) = self.candidate_targets().await;
+        let mut this = self.clone();
+        loop {
+            this.targets = Some(targets.clone());
+            let res = this
+                .write_resolved_once_to(client, resolved.clone(), target_binary)
+                .await;
+            let Err(e) = &res else {
+                return res;
+            };
+            let Some(Error::SmokeTestFailed { target, .. }) = e.downcast_ref() else {
+                return res;
+            };
+            let Some(failed) = targets.iter().position(|t| t == target) else {
+                return res;
+            };
+            targets.drain(..=failed);
+            if targets.is_empty() {
+                return res;
+            }
+
+            self.stderr().execute(Print(
+                " > Smoke test failed, rolled back and trying next target...\n"
+                    .red()
+                    .italic(),
+            ))?;
+            // The installation was already confirmed.
+            this.no_confirm = true;
+        }
+    }
+    async fn write_resolved_once_to(
+        &self,
+        client: &Client,
+        resolved: Resolved,
+        target_binary: &Path,
+    ) -> Result<SwapOutcome> {
+        let name = file_name(target_binary)?;
+
+        let temp = self.scratch_dir()?;
+
+        if !self.dry_run {
+            self.recover(target_binary).await?;
+        }
+        let skip_reason = match self.roll_back_unhealthy(target_binary, temp.path()).await? {
+            Some(version) if version == resolved.version => Some(SkipReason::Unhealthy { version }),
+            _ => self.skip_reason(
+                &resolved,
+                self.current_version_of(Some(target_binary)).as_deref(),
+            )?,
+        };
+        if let Some(reason) = skip_reason {
+            self.print_skipped(name, &reason)?;
+            return Ok(SwapOutcome {
+                version: resolved.version,
+                build_id: resolved.build_id,
+                installed_path: target_binary.to_path_buf(),
+                target_used: String::new(),
+                bytes_written: 0,
+                bytes_downloaded: 0,
+                download_duration: None,
+                dry_run: self.dry_run,
+                status: SwapStatus::Skipped { reason },
+                companion_paths: vec![],
+                previous_version: None,
+            });
+        }
+
+        let release = resolved.release.clone();
+        let fetched = self.fetch_bin(client, resolved, temp.path()).await?;
+        self.install_fetched(
+            client,
+            release.as_ref(),
+            fetched,
+            temp.path(),
+            target_binary,
+        )
+        .await
+    }
+
+    /// Checks the binary `fetched` into `temp`, and writes it to
+    /// `target_binary` once confirmed. Companion assets of the `release` are
+    /// installed alongside it.
+    pub(crate) async fn install_fetched(
+        &self,
+        client: &Client,
+        release: Option<&ReleaseInfo>,
+        fetched: Fetched<PathBuf>,
+        temp: &Path,
+        target_binary: &Path,
+    ) -> Result<SwapOutcome> {
+        let name = file_name(target_binary)?;
+        let bin_path = temp.join(&fetched.found);
+
+        let mut outcome = self.check_fetched(fetched, temp, target_binary).await?;
+        if matches!(outcome.status, SwapStatus::Skipped { .. }) {
+            return Ok(outcome);
+        }
+
+        self.stderr()
+            .execute(Print("\n  About to write binary to ".green()))?
+            .execute(Print(format!("`{}`\n", target_binary.display())))?;
+
+        if self.dry_run {
+            outcome.previous_version = match self.current_version_of(Some(target_binary)) {
+                Some(current_version) => Some(current_version),
+                None => guess_version(target_binary).await,
+            };
+            if let Some(previous_version) = &outcome.previous_version {
+                self.stderr()
+                    .execute(Print("  Would update ".green()))?
+                    .execute(Print(name))?
+                    .execute(Print(" from ".green()))?
+                    .execute(Print(previous_version))?
+                    .execute(Print(" to ".green()))?
+                    .execute(Print(&outcome.version))?
+                    .execute(Print("\n"))?
+                    .execute(ResetColor)?;
+            }
+        }
+
+        if self.confirm(target_binary, &outcome.version).await? {
+            if !self.dry_run {
+                let Some(swapped) = self
+                    .swap_checked(&mut outcome, &bin_path, temp, target_binary)
+                    .await?
+                else {
+                    return Ok(outcome);
+                };
+                self.finish_swap(swapped, &outcome.version).await?;
+                if let Some(release) = release {
+                    outcome.companion_paths = self.install_companions(client, release).await?;
+                }
+            }
+
+            self.print_updated(name)?;
+            outcome.status = SwapStatus::Swapped;
+        }
 
-        let version = if let Some(v) = self.version.clone() {
-            v
+        Ok(outcome)
+    }
+
+    /// Checks the binary `fetched` into `temp` before it is written to
+    /// `target_binary`. The returned outcome is skipped if the binary is
+    /// identical to the installed one, and declined otherwise, until it is
+    /// swapped in.
+    async fn check_fetched(
+        &self,
+        fetched: Fetched<PathBuf>,
+        temp: &Path,
+        target_binary: &Path,
+    ) -> Result<SwapOutcome> {
+        let name = file_name(target_binary)?;
+        let bin_path = temp.join(&fetched.found);
+
+        let mut outcome = SwapOutcome {
+            version: fetched.version,
+            build_id: fetched.build_id,
+            installed_path: target_binary.to_path_buf(),
+            target_used: fetched.target,
+            bytes_written: tokio::fs::metadata(&bin_path).await?.len(),
+            bytes_downloaded: fetched.download_bytes,
+            download_duration: fetched.download_duration,
+            dry_run: self.dry_run,
+            status: SwapStatus::Declined,
+            companion_paths: vec![],
+            previous_version: None,
+        };
+
+        self.check_size(&bin_path).await?;
+        self.check_static(&bin_path)?;
+
+        if self.skip_if_identical && is_identical(&bin_path, target_binary).await? {
+            let reason = SkipReason::Identical;
+            self.print_skipped(name, &reason)?;
+            outcome.status = SwapStatus::Skipped { reason };
+            return Ok(outcome);
+        }
+
+        // The check command runs the binary, which has to be executable.
+        make_executable(&bin_path, target_binary).await?;
+        self.check(&bin_path).await?;
+
+        Ok(outcome)
+    }
+
+    /// Swaps the checked binary at `bin_path` in for `target_binary`, once
+    /// the installation has been confirmed. Returns `None`, with the status
+    /// of `outcome` set, if the binary was updated while waiting for the lock
+    /// or `on_before_swap` declined it. The lock is held and the replaced
+    /// binary kept until the swap is finished with
+    /// [`finish_swap`](Self::finish_swap).
+    async fn swap_checked(
+        &self,
+        outcome: &mut SwapOutcome,
+        bin_path: &Path,
+        temp: &Path,
+        target_binary: &Path,
+    ) -> Result<Option<Swapped>> {
+        if self.create_dirs {
+            if let Some(parent) = target_binary.parent() {
+                create_dir_all(parent).await?;
+            }
+        }
+        let backup_bin = if self.provisional {
+            health::backup_path(target_binary)
         } else {
-            #[derive(Debug, Deserialize)]
-            struct Response {
-                tag_name: String,
+            recover::swap_backup_path(target_binary)
+        };
+        let lock = self.lock(target_binary).await?;
+        if self
+            .updated_while_waiting(lock.as_ref(), bin_path, target_binary, &outcome.version)
+            .await?
+        {
+            let reason = SkipReason::UpToDate;
+            self.print_skipped(file_name(target_binary)?, &reason)?;
+            outcome.status = SwapStatus::Skipped { reason };
+            return Ok(None);
+        }
+        if let Some(on_before_swap) = &self.on_before_swap {
+            outcome.status = SwapStatus::Swapped;
+            if !on_before_swap(outcome).await {
+                outcome.status = SwapStatus::Declined;
+                return Ok(None);
             }
+        }
+        swap_verifying(bin_path, target_binary, &backup_bin, self.verify_backup).await?;
+        if let Err(e) = self.smoke_test(target_binary, &outcome.target_used).await {
+            if tokio::fs::try_exists(&backup_bin).await? {
+                swap(&backup_bin, target_binary, &temp.join("failed-binary"))
+                    .await
+                    .wrap_err("failed to roll back after the smoke test failed")?;
+            } else {
+                tokio::fs::remove_file(target_binary).await?;
+            }
+            return Err(e);
+        }
+
+        Ok(Some(Swapped {
+            target_binary: target_binary.to_path_buf(),
+            backup_bin,
+            _lock: lock,
+        }))
+    }
+
+    /// Finishes the swap of the binary of `version`, keeping the replaced
+    /// binary at `keep_backup` and for `provisional` updates, or removing it
+    /// otherwise, and releasing the lock.
+    async fn finish_swap(&self, swapped: Swapped, version: &str) -> Result<()> {
+        let Swapped {
+            target_binary,
+            backup_bin,
+            ..
+        } = &swapped;
+        if let Some(keep_backup) = &self.keep_backup {
+            let keep_backup = if keep_backup.is_dir() {
+                keep_backup.join(target_binary.file_name().unwrap_or_default())
+            } else {
+                keep_backup.clone()
+            };
+            if tokio::fs::try_exists(backup_bin).await? {
+                tokio::fs::copy(backup_bin, &keep_backup)
+                    .await
+                    .wrap_err_with(|| {
+                        format!(
+                            "failed to keep the previous binary at `{}`",
+                            keep_backup.display()
+                        )
+                    })?;
+            }
+        }
+        if self.provisional {
+            self.mark_pending(target_binary, version).await?;
+        } else {
+            recover::remove_old_binary(backup_bin).await?;
+        }
+        self.emit(SwapEvent::Swapped(target_binary.clone()));
+        Ok(())
+    }
+
+    /// Downloads the release and extracts `bin_name` from it into `temp`.
+    pub(crate) async fn fetch_bin(
+        &self,
+        client: &Client,
+        resolved: Resolved,
+        temp: &Path,
+    ) -> Result<Fetched<PathBuf>> {
+        if let Some(package) = self.distro_package {
+            return self
+                .fetch_distro_package(client, resolved, package, temp)
+                .await;
+        }
+
+        let version = resolved.version.clone();
+        self.fetch_matching(
+            client,
+            resolved,
+            temp,
+            |target, bin_dir, extracted_files| {
+                self.locate_bin(
+                    extracted_files,
+                    temp,
+                    bin_dir,
+                    &version,
+                    target,
+                    &self.bin_name,
+                )
+            },
+        )
+        .await
+    }
+
+    /// Finds the binary `bin` in the extracted asset for `target`, at the
+    /// `bin_dir` template if it is given, if it runs on this host.
+    fn locate_bin(
+        &self,
+        extracted_files: &dyn ExtractedTree,
+        temp: &Path,
+        bin_dir: Option<&str>,
+        version: &str,
+        target: &str,
+        bin: &str,
+    ) -> Option<PathBuf> {
+        let path = match bin_dir {
+            Some(bin_dir) => {
+                let path = self.bin_dir_path(bin_dir, version, target, bin);
+                extracted_files.is_file(&path).then_some(path)?
+            }
+            None => {
+                let bin = Path::new(bin);
+                let bin = if target.contains("windows") {
+                    Cow::Owned(bin.with_extension("exe"))
+                } else {
+                    Cow::Borrowed(bin)
+                };
+                find_bin(extracted_files, &bin, self.max_search_depth)?
+            }
+        };
+        self.runs_on_host(&temp.join(&path)).then_some(path)
+    }
+    /// A new temporary directory to download and extract assets in, inside
+    /// of `temp_dir` if set.
+    pub(crate) fn scratch_dir(&self) -> io::Result<tempfile::TempDir> {
+        let mut builder = tempfile::Builder::new();
+        builder.prefix("binswap");
+        match &self.temp_dir {
+            Some(dir) => builder.tempdir_in(dir),
+            None => builder.tempdir(),
+        }
+    }
+    /// The path of the currently executed binary.
+    fn current_exe(&self) -> Result<PathBuf> {
+        match &self.current_exe_override {
+            Some(path) => Ok(path.clone()),
+            None => env::current_exe().wrap_err("failed to determine the current executable"),
+        }
+    }
+    /// Downloads the release and writes every binary in it whose file name
+    /// matches `bin_glob` into the directory `dir`. If no `bin_glob` is given,
+    /// only `bin_name` is installed.
+    pub async fn fetch_and_install_all_into(
+        &self,
+        dir: impl AsRef<Path>,
+    ) -> Result<InstallAllOutcome> {
+        let dir = dir.as_ref();
+        let install_dir = dir.to_path_buf();
+        let outcome = self
+            .measured(
+                |this| async move { this.install_all_into(&install_dir).await },
+                |outcome| (outcome.version.clone(), outcome.status.clone()),
+            )
+            .await?;
+        if outcome.status == SwapStatus::Swapped {
+            self.add_dir_to_user_path(dir)?;
+        }
+        Ok(outcome)
+    }
+    async fn install_all_into(&self, dir: &Path) -> Result<InstallAllOutcome> {
+        let pattern = match &self.bin_glob {
+            Some(glob) => glob::Pattern::new(glob)
+                .wrap_err_with(|| format!("invalid binary glob `{glob}`"))?,
+            None => glob::Pattern::new(&glob::Pattern::escape(&self.bin_name))?,
+        };
+
+        self.install_each(
+            dir,
+            |temp, _, target, _, extracted_files| {
+                let is_windows = target.contains("windows");
+                let mut matched = vec![];
+                find_files(extracted_files, Path::new("."), &mut |path| {
+                    let file_name = path.file_name().unwrap().to_str().unwrap_or_default();
+                    let stem = if is_windows {
+                        file_name.strip_suffix(".exe").unwrap_or(file_name)
+                    } else {
+                        file_name
+                    };
+                    if (pattern.matches(file_name) || pattern.matches(stem))
+                        && self.runs_on_host(&temp.join(path))
+                    {
+                        matched.push(path.to_path_buf());
+                    }
+                });
+                matched.sort();
+                (!matched.is_empty()).then_some(matched)
+            },
+            |_, path| dir.join(path.file_name().unwrap()),
+        )
+        .await
+    }
+    /// Downloads the release and writes each of the binaries in `bin_names`
+    /// to its path in `destinations`, for releases bundling several tools in
+    /// one asset. Like `bin_name`, the names are looked up anywhere in the
+    /// asset.
+    ///
+    /// Nothing is written unless every binary is found in the same asset, and
+    /// if writing one of them fails, the ones already written are rolled back.
+    pub async fn fetch_and_write_bins_to(
+        &self,
+        bin_names: Vec<String>,
+        destinations: HashMap<String, PathBuf>,
+    ) -> Result<InstallAllOutcome> {
+        if bin_names.is_empty() {
+            return Err(eyre!("no binaries were given to install"));
+        }
+        if let Some(name) = bin_names
+            .iter()
+            .find(|name| !destinations.contains_key(*name))
+        {
+            return Err(eyre!("no destination was given for the binary `{name}`"));
+        }
+
+        self.measured(
+            |this| async move { this.write_bins_to(&bin_names, &destinations).await },
+            |outcome| (outcome.version.clone(), outcome.status.clone()),
+        )
+        .await
+    }
+    async fn write_bins_to(
+        &self,
+        bin_names: &[String],
+        destinations: &HashMap<String, PathBuf>,
+    ) -> Result<InstallAllOutcome> {
+        self.install_each(
+            &destinations[&bin_names[0]],
+            |temp, version, target, bin_dir, extracted_files| {
+                bin_names
+                    .iter()
+                    .map(|name| {
+                        self.locate_bin(extracted_files, temp, bin_dir, version, target, name)
+                    })
+                    .collect()
+            },
+            |i, _| destinations[&bin_names[i]].clone(),
+        )
+        .await
+    }
+    /// Downloads the release, finds the binaries to install in it with
+    /// `locate`, given the directory it is extracted to, the version, the
+    /// target and the `bin_dir` template for it, and installs each of them
+    /// like a single binary to the path given by `destination` for its index
+    /// and path in the asset. `confirm_path` is the path shown when asking
+    /// for confirmation.
+    async fn install_each(
+        &self,
+        confirm_path: &Path,
+        mut locate: impl FnMut(
+            &Path,
+            &str,
+            &str,
+            Option<&str>,
+            &dyn ExtractedTree,
+        ) -> Option<Vec<PathBuf>>,
+        destination: impl Fn(usize, &Path) -> PathBuf,
+    ) -> Result<InstallAllOutcome> {
+        if self.distro_package.is_some() {
+            return Err(eyre!(
+                "installing several binaries is not supported for distribution packages"
+            ));
+        }
+
+        let temp = self.scratch_dir()?;
+
+        self.print_updating(&self.repo_name)?;
+
+        let client = self.client()?;
+        let resolved = self.resolve(&client).await?;
+
+        if let Some(reason) = self.skip_reason(&resolved, self.current_version.as_deref())? {
+            self.print_skipped(&self.repo_name, &reason)?;
+            return Ok(InstallAllOutcome {
+                version: resolved.version,
+                build_id: resolved.build_id,
+                target: String::new(),
+                matched: vec![],
+                installed: vec![],
+                bytes_downloaded: 0,
+                download_duration: None,
+                dry_run: self.dry_run,
+                status: SwapStatus::Skipped { reason },
+                companion_paths: vec![],
+            });
+        }
 
-            stderr()
+        let release = resolved.release.clone();
+        let version = resolved.version.clone();
+        let fetched = self
+            .fetch_matching(
+                &client,
+                resolved,
+                temp.path(),
+                |target, bin_dir, extracted_files| {
+                    locate(temp.path(), &version, target, bin_dir, extracted_files)
+                },
+            )
+            .await?;
+
+        let destinations: Vec<PathBuf> = fetched
+            .found
+            .iter()
+            .enumerate()
+            .map(|(i, path)| destination(i, path))
+            .collect();
+
+        // Each binary is checked and swapped in like a single one, but the
+        // installation is only confirmed once, and the replaced binaries are
+        // kept until every binary is in place, to roll back to.
+        let mut checked = vec![];
+        for (path, destination) in fetched.found.iter().zip(&destinations) {
+            let one = Fetched {
+                version: fetched.version.clone(),
+                build_id: fetched.build_id.clone(),
+                target: fetched.target.clone(),
+                found: path.clone(),
+                download_bytes: fetched.download_bytes,
+                download_duration: fetched.download_duration,
+            };
+            checked.push(self.check_fetched(one, temp.path(), destination).await?);
+        }
+
+        self.stderr()
+            .execute(Print("\n  About to write binaries to\n".green()))?;
+        for destination in &destinations {
+            self.stderr()
+                .execute(Print(format!("    `{}`\n", destination.display())))?;
+        }
+
+        let mut outcome = InstallAllOutcome {
+            version: fetched.version,
+            build_id: fetched.build_id,
+            target: fetched.target,
+            matched: fetched.found,
+            installed: vec![],
+            bytes_downloaded: fetched.download_bytes,
+            download_duration: fetched.download_duration,
+            dry_run: self.dry_run,
+            status: SwapStatus::Declined,
+            companion_paths: vec![],
+        };
+
+        if self.confirm(confirm_path, &outcome.version).await? {
+            let mut swapped: Vec<Swapped> = vec![];
+            for ((path, destination), mut one) in
+                outcome.matched.iter().zip(&destinations).zip(checked)
+            {
+                if matches!(one.status, SwapStatus::Skipped { .. }) {
+                    continue;
+                }
+                if !self.dry_run {
+                    self.recover(destination).await?;
+                    let res = self
+                        .swap_checked(&mut one, &temp.path().join(path), temp.path(), destination)
+                        .await;
+                    match res {
+                        Ok(Some(one)) => swapped.push(one),
+                        Ok(None) if matches!(one.status, SwapStatus::Skipped { .. }) => continue,
+                        Ok(None) => {
+                            roll_back_swapped(&swapped, temp.path()).await?;
+                            return Ok(outcome);
+                        }
+                        Err(e) => {
+                            roll_back_swapped(&swapped, temp.path()).await?;
+                            return Err(e);
+                        }
+                    }
+                }
+                outcome.installed.push(destination.clone());
+            }
+            for one in swapped {
+                self.finish_swap(one, &outcome.version).await?;
+            }
+            match &release {
+                Some(release) if !self.dry_run => {
+                    outcome.companion_paths = self.install_companions(&client, release).await?;
+                }
+                _ => {}
+            }
+
+            self.print_updated(&self.repo_name)?;
+            outcome.status = SwapStatus::Swapped;
+        }
+
+        Ok(outcome)
+    }
+
+    /// Determines the version to install, and checks that its release may be
+    /// installed.
+    async fn resolve(&self, client: &Client) -> Result<Resolved> {
+        if self.cosign_verify.is_some() {
+            return Err(Error::Unsupported {
+                feature: "cosign bundle verification".to_string(),
+            }
+            .into());
+        }
+
+        let started = Instant::now();
+        let (version, release) = if let Some(tag) = self.rolling_tag.clone() {
+            self.emit(SwapEvent::ResolvingVersion);
+            let release = self.fetch_release_by_tag(client, &tag).await?;
+            (tag, Some(release))
+        } else if let Some(req) = self.version.as_deref().and_then(release::version_range) {
+            self.emit(SwapEvent::ResolvingVersion);
+            self.stderr()
+                .execute(Print(
+                    format!("Finding the newest version matching {req}...\n")
+                        .magenta()
+                        .italic(),
+                ))?
+                .execute(ResetColor)?;
+
+            let release = self.fetch_release_matching(&req).await?;
+            (release.version().to_string(), Some(release))
+        } else if let Some(v) = self.version.clone() {
+            let release = if self.needs_release() {
+                Some(self.fetch_release_for_version(client, &v).await?)
+            } else {
+                None
+            };
+            (v, release)
+        } else {
+            self.emit(SwapEvent::ResolvingVersion);
+            self.stderr()
                 .execute(Print(
                     "Getting latest version number...\n".magenta().italic(),
                 ))?
                 .execute(ResetColor)?;
 
-            let res: Response = client
-                .get(Url::parse(&format!(
-                    "https://api.github.com/repos/{}/{}/releases/latest",
-                    self.repo_author, self.repo_name
-                ))?)
-                .send(true)
-                .await?
-                .json()
-                .await?;
-            res.tag_name.trim_start_matches('v').to_string()
+            let release = self.fetch_latest_release(client).await?;
+            (release.version().to_string(), Some(release))
         };
 
-        stderr()
+        self.emit(SwapEvent::VersionResolved(version.clone()));
+        self.stderr()
             .execute(Print("Using version ".green()))?
             .execute(Print(&version))?
             .execute(Print("\n"))?
             .execute(ResetColor)?;
 
-        let targets = if let Some(targets) = self.targets.clone() {
-            targets
-        } else {
-            get_desired_targets(None).get().await.to_vec()
-        };
-        let data = Arc::new(Data::new(
-            self.asset_name
-                .as_deref()
-                .map(Into::into)
-                .unwrap_or_else(|| self.bin_name.as_str().into()),
-            version.into(),
-            Some(format!(
-                "https://github.com/{}/{}/",
-                self.repo_author, self.repo_name
-            )),
-        ));
-        for target in &targets {
-            let resolver = GhCrateMeta::new(
-                client.clone(),
-                gh_api_client.clone(),
-                data.clone(),
-                Arc::new(TargetData {
-                    target: target.into(),
-                    meta: PkgMeta::default(),
-                }),
-            );
+        if let Some(release) = &release {
+            if release.prerelease && self.forbid_prerelease && !self.allow_prerelease {
+                return Err(Error::PrereleaseNotAllowed {
+                    tag: release.tag_name.clone(),
+                }
+                .into());
+            }
+        }
 
-            stderr()
-                .execute(Print("Looking for binary for target ".magenta().italic()))?
-                .execute(Print(&target))?
-                .execute(Print("...\n".magenta().italic()))?;
+        if self.require_verified_tag {
+            let tag = release.as_ref().map(|release| release.tag_name.as_str());
+            self.verify_tag(client, &version, tag).await?;
+        }
 
-            let found = Arc::clone(&resolver).find().await??;
-            if !found {
-                continue;
+        let build_id = release.as_ref().and_then(|release| {
+            release
+                .assets
+                .iter()
+                .filter_map(|asset| asset.updated_at.clone())
+                .max()
+        });
+
+        self.record(|metrics| metrics.resolve_duration = Some(started.elapsed()));
+
+        Ok(Resolved {
+            version,
+            build_id,
+            release,
+        })
+    }
+
+    /// Whether the metadata of a release given by `version` has to be
+    /// fetched, rather than just using the version.
+    fn needs_release(&self) -> bool {
+        self.install_if.is_some()
+            || self.min_release_age.is_some()
+            || self.expected_content_type.is_some()
+            || self.distro_package.is_some()
+            || self.forbid_prerelease
+            || !self.companion_assets.is_empty()
+            || !self.extension_format_map.is_empty()
+            || self.allowed_download_hosts.is_some()
+            || self.has_asset_filters()
+            || self.require_archive_entry.is_some()
+            || self.verify_checksum.is_some()
+            || self.pub_key.is_some()
+            || self.forge != ForgeKind::GitHub
+    }
+
+    fn has_asset_filters(&self) -> bool {
+        !self.asset_must_contain.is_empty() || !self.asset_must_not_contain.is_empty()
+    }
+
+    /// The version of the binary being replaced, from `current_version` or
+    /// `detect_current_version`. `target_binary` is the binary being
+    /// replaced, if there is just one.
+    fn current_version_of(&self, target_binary: Option<&Path>) -> Option<String> {
+        match (&self.current_version, &self.detect_current_version) {
+            (Some(current_version), _) => Some(current_version.clone()),
+            (None, Some(detect)) => target_binary.and_then(&**detect),
+            (None, None) => None,
+        }
+    }
+    /// Why the resolved release should not be installed, if it should not.
+    /// Fails with [`Error::Downgrade`] if it is older than `current_version`,
+    /// unless `allow_downgrade` is set.
+    fn skip_reason(
+        &self,
+        resolved: &Resolved,
+        current_version: Option<&str>,
+    ) -> Result<Option<SkipReason>> {
+        if self.rolling_tag.is_some()
+            && self.installed_build.is_some()
+            && self.installed_build == resolved.build_id
+        {
+            return Ok(Some(SkipReason::UpToDate));
+        }
+
+        if let Some(current) = current_version {
+            if same_version(current, &resolved.version) {
+                return Ok(Some(SkipReason::UpToDate));
+            }
+            if !self.allow_downgrade && is_older(&resolved.version, current) {
+                return Err(Error::Downgrade {
+                    current: current.to_string(),
+                    resolved: resolved.version.clone(),
+                }
+                .into());
             }
+        }
 
-            stderr().execute(Print("Found a binary! Downloading...\n".magenta().italic()))?;
+        let Some(release) = &resolved.release else {
+            return Ok(None);
+        };
 
-            let extracted_files = resolver.fetch_and_extract(temp.path()).await?;
+        if let Some(install_if) = &self.install_if {
+            if !install_if(release) {
+                return Ok(Some(SkipReason::Rejected));
+            }
+        }
 
-            let bin_name = Path::new(&self.bin_name);
+        if let Some(min_age) = self.min_release_age {
+            let published_at = release
+                .published_at
+                .as_deref()
+                .and_then(|published_at| humantime::parse_rfc3339(published_at).ok());
+            let age = published_at
+                .and_then(|published_at| SystemTime::now().duration_since(published_at).ok());
+            let old_enough = matches!(age, Some(age) if age >= min_age);
+            if !old_enough {
+                return Ok(Some(SkipReason::TooRecent {
+                    published_at: release.published_at.clone(),
+                }));
+            }
+        }
 
-            let bin_name = if target.contains("windows") {
-                Cow::Owned(bin_name.with_extension("exe"))
-            } else {
-                Cow::Borrowed(bin_name)
-            };
+        Ok(None)
+    }
 
-            let bin_path = if extracted_files.has_file(&bin_name) {
-                temp.path().join(&bin_name)
-            } else {
-                let res = (|| {
-                    let entries = extracted_files.get_dir(Path::new("."))?;
-                    for entry in entries {
-                        if let Some(ExtractedFilesEntry::Dir(entries)) =
-                            extracted_files.get_entry(Path::new(entry))
-                        {
-                            if entries.contains(bin_name.as_os_str()) {
-                                let mut p = temp.path().join(Path::new(&**entry));
-                                p.push(&bin_name);
-                                return Some(p);
-                            }
-                        }
-                    }
+    /// Looks through the candidate targets for a release asset of the
+    /// resolved version in which `locate` finds what it is looking for, given
+    /// the target and its `bin_dir` template, from the `cargo-binstall`
+    /// metadata or overridden by `bin_dir`. The asset is extracted into
+    /// `temp`.
+    async fn fetch_matching<T>(
+        &self,
+        client: &Client,
+        resolved: Resolved,
+        temp: &Path,
+        mut locate: impl FnMut(&str, Option<&str>, &dyn ExtractedTree) -> Option<T>,
+    ) -> Result<Fetched<T>> {
+        let version = resolved.version.clone();
+        let build_id = resolved.build_id.clone();
+        let prober = self.prober(client, resolved).await?;
+        let release = &prober.release;
 
-                    None
-                })();
+        let (targets, total) = self.candidate_targets().await;
+        let tried = targets.len();
+        let probes = stream::iter(targets)
+            .map(|target| {
+                let prober = &prober;
+                async move {
+                    let probe = self.probe(prober, &target).await;
+                    (target, probe)
+                }
+            })
+            .buffered(self.probe_concurrency.max(1));
+        pin_mut!(probes);
+        let (mut download_bytes, mut download_duration) = (0, None);
+        // The first asset skipped for having no signature, when `pub_key`
+        // is set, such that signed assets are preferred.
+        let mut unsigned = None;
+        while let Some((target, probe)) = probes.next().await {
+            let Some(Probe { resolver, asset }) = probe? else {
+                continue;
+            };
 
-                if let Some(bin_path) = res {
-                    bin_path
-                } else {
-                    stderr().execute(Print(
-                        " > No binary found in asset, trying next target...\n"
+            if let (Some(release), Some((asset, _)), Some(_)) = (release, asset, &self.pub_key) {
+                if !minisign::has_signature(release, asset) {
+                    unsigned.get_or_insert_with(|| asset.name.clone());
+                    self.stderr().execute(Print(
+                        " > Asset has no signature, trying next target...\n"
                             .red()
                             .italic(),
                     ))?;
                     continue;
                 }
-            };
+            }
 
-            if !self.no_check_with_cmd {
-                let res = tokio::process::Command::new(&bin_path)
-                    .arg(&self.check_with_cmd)
-                    .output()
-                    .await?;
-                if !res.status.success() {
+            if let Some(expected) = &self.expected_content_type {
+                if let Some((asset, _)) = asset {
+                    if &asset.content_type != expected {
+                        return Err(Error::UnexpectedAssetContentType {
+                            asset: asset.name.clone(),
+                            content_type: asset.content_type.clone(),
+                            expected: expected.clone(),
+                        }
+                        .into());
+                    }
+                }
+            }
+
+            self.emit(SwapEvent::Downloading {
+                target: target.clone(),
+            });
+            self.stderr()
+                .execute(Print("Found a binary! Downloading...\n".magenta().italic()))?;
+
+            let total = asset.map(|(asset, _)| asset.size).filter(|&size| size > 0);
+            self.progress(ProgressEvent::Downloading {
+                downloaded: 0,
+                total,
+            });
+            let started = Instant::now();
+            let pkg_fmt = resolver.pkg_fmt();
+            let verifies = self.verify_checksum.is_some() || self.pub_key.is_some();
+            let checks = verifies || self.allowed_download_hosts.is_some();
+            let extracted_files: Box<dyn ExtractedTree> = match asset {
+                // Assets are extracted while being downloaded by the fetcher,
+                // which does not report how far it has come, nor leaves the
+                // download to be checked. When progress is reported, or the
+                // asset or its host is checked, it is downloaded to a file
+                // instead, checked and then extracted from that same file.
+                Some((asset, _)) if self.on_progress.is_some() || checks => {
+                    let url = Url::parse(&asset.browser_download_url)?;
+                    let download = self.scratch_dir()?;
+                    let archive = download.path().join(&asset.name);
+                    self.retrying(|| self.download_with_progress(client, &url, &archive, total))
+                        .await?;
+                    if verifies {
+                        let Some(release) = release else {
+                            return Err(eyre!(
+                                "could not determine the release of `{}` to verify it",
+                                asset.name
+                            ));
+                        };
+                        self.verify_checksum(client, release, asset, &archive)
+                            .await?;
+                        self.verify_signature(client, release, asset, &archive)
+                            .await?;
+                    }
+                    Box::new(self.extract_asset(&archive, pkg_fmt, &target, temp).await?)
+                }
+                None if checks => {
                     return Err(eyre!(
-                        "Could not execute `{}` on downloaded binary: {}",
-                        self.check_with_cmd,
-                        res.status,
+                        "could not determine the downloaded asset for `{target}` to check it"
                     ));
                 }
+                _ => {
+                    let extracted_files = self
+                        .retrying(|| async { Ok(resolver.fetch_and_extract(temp).await?) })
+                        .await?;
+                    if let Some(total) = total {
+                        self.progress(ProgressEvent::Downloading {
+                            downloaded: total,
+                            total: Some(total),
+                        });
+                    }
+                    Box::new(extracted_files)
+                }
+            };
+            let elapsed = started.elapsed();
+            let extracted_bytes = extracted_size(temp, &*extracted_files).await;
+            self.record_download(elapsed, extracted_bytes);
+            download_bytes += total.unwrap_or(extracted_bytes);
+            *download_duration.get_or_insert(Duration::ZERO) += elapsed;
+            self.check_extracted_entries(&*extracted_files)?;
+
+            let bin_dir = resolver.target_meta().bin_dir;
+            if let Some(found) = locate(&target, bin_dir.as_deref(), &*extracted_files) {
+                return Ok(Fetched {
+                    version,
+                    build_id,
+                    target,
+                    found,
+                    download_bytes,
+                    download_duration,
+                });
             }
 
-            stderr()
-                .execute(Print("\n  About to write binary to ".green()))?
-                .execute(Print(format!("`{}`\n", target_binary.display())))?;
+            self.stderr().execute(Print(
+                " > No binary found in asset, trying next target...\n"
+                    .red()
+                    .italic(),
+            ))?;
+        }
 
-            if self.no_confirm || confirm().await {
-                if !self.dry_run {
-                    let backup_bin = temp.path().join("backup-binary");
-
-                    // NOTE: Swapping procedure:
-                    // - Move the old binary into a temp folder
-                    // - Move the new binary into target destination, which
-                    //   should now be vacant
-                    //   - If this fails, move the old binary back
-                    // - The temp folder will be dropped at the end of
-                    //   scope, removing the old binary
-                    tokio::fs::rename(target_binary, &backup_bin)
-                        .await
-                        .wrap_err("failed to move old binary before updating to new")?;
-                    if let Err(e) = tokio::fs::rename(bin_path, target_binary).await {
-                        if let Err(e2) = tokio::fs::rename(backup_bin, target_binary).await {
-                            let error_msg = "failed to move old binary back after failing to move new binary into target destination";
-                            return Err(e2).wrap_err(error_msg).wrap_err(e);
-                        } else {
-                            return Err(e)
-                                .wrap_err("failed to put new binary into target destination");
-                        }
+        if let Some(asset) = unsigned {
+            return Err(Error::BadSignature {
+                asset,
+                reason: "the release has no signature for it".to_string(),
+            }
+            .into());
+        }
+        Err(Error::AssetNotFound {
+            version,
+            tried,
+            total,
+        }
+        .into())
+    }
+
+    fn print_updating(&self, name: &str) -> io::Result<()> {
+        self.stderr()
+            .execute(Print("Updating ".green()))?
+            .execute(Print(name))?
+            .execute(Print("...\n".green()))?
+            .execute(ResetColor)?;
+
+        Ok(())
+    }
+
+    fn print_skipped(&self, name: &str, reason: &SkipReason) -> io::Result<()> {
+        self.stderr()
+            .execute(Print("\n".green()))?
+            .execute(Print(name))?
+            .execute(Print(" was not updated: ".green()))?
+            .execute(Print(reason))?
+            .execute(Print("\n"))?
+            .execute(ResetColor)?;
+
+        Ok(())
+    }
+
+    fn print_updated(&self, name: &str) -> io::Result<()> {
+        self.stderr()
+            .execute(Print("\n".green()))?
+            .execute(Print(name))?
+            .execute(Print(" has been updated!".green()))?
+            .execute(Print(
+                if self.dry_run {
+                    " (not actually since it was a dry-run)"
+                } else {
+                    ""
+                }
+                .dim(),
+            ))?
+            .execute(Print("\n"))?
+            .execute(ResetColor)?;
+
+        Ok(())
+    }
+}
+
+type InstallIf = dyn Fn(&ReleaseInfo) -> bool + Send + Sync;
+type DetectVersion = dyn Fn(&Path) -> Option<String> + Send + Sync;
+type ConfirmWith = dyn for<'a> Fn(&'a ConfirmContext) -> BoxFuture<'a, bool> + Send + Sync;
+type BeforeSwap = dyn for<'a> Fn(&'a SwapOutcome) -> BoxFuture<'a, bool> + Send + Sync;
+type OnMetrics = dyn Fn(UpdateMetrics) + Send + Sync;
+type OnProgress = dyn Fn(ProgressEvent) + Send + Sync;
+
+/// The version to install.
+#[derive(Debug, Clone)]
+struct Resolved {
+    version: String,
+    /// The newest update time of the assets of the release, if known.
+    build_id: Option<String>,
+    /// The release of the version, if it was fetched.
+    release: Option<ReleaseInfo>,
+}
+
+/// A release asset which has been downloaded and extracted.
+struct Fetched<T> {
+    version: String,
+    build_id: Option<String>,
+    target: String,
+    found: T,
+    /// The number of bytes downloaded, over all of the assets tried.
+    download_bytes: u64,
+    /// How long downloading and extracting the assets took, if any were
+    /// downloaded.
+    download_duration: Option<Duration>,
+}
+
+fn file_name(target_binary: &Path) -> Result<&str> {
+    Ok(target_binary
+        .file_name()
+        .ok_or_else(|| eyre!("target file had no name"))?
+        .to_str()
+        .unwrap())
+}
+
+/// The files of an extracted asset, as needed to search them for the binary.
+pub(crate) trait ExtractedTree {
+    /// Whether `path`, relative to the root, is a file.
+    fn is_file(&self, path: &Path) -> bool;
+    /// Whether `path`, relative to the root, is a directory.
+    fn is_dir(&self, path: &Path) -> bool;
+    /// The names of the entries in the directory `path`, relative to the
+    /// root, or `None` if it is not a directory.
+    fn entries(&self, path: &Path) -> Option<Vec<OsString>>;
+}
+
+impl ExtractedTree for ExtractedFiles {
+    fn is_file(&self, path: &Path) -> bool {
+        self.has_file(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        matches!(self.get_entry(path), Some(ExtractedFilesEntry::Dir(_)))
+    }
+
+    fn entries(&self, path: &Path) -> Option<Vec<OsString>> {
+        let entries = self.get_dir(path)?;
+        Some(entries.iter().map(|entry| entry.to_os_string()).collect())
+    }
+}
+
+/// An asset extracted into the directory, which was not extracted by a
+/// fetcher.
+struct ExtractedDir(PathBuf);
+
+impl ExtractedTree for ExtractedDir {
+    fn is_file(&self, path: &Path) -> bool {
+        self.0.join(path).is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.0.join(path).is_dir()
+    }
+
+    fn entries(&self, path: &Path) -> Option<Vec<OsString>> {
+        let entries = std::fs::read_dir(self.0.join(path)).ok()?;
+        entries.map(|entry| Some(entry.ok()?.file_name())).collect()
+    }
+}
+
+/// Finds `bin_name` in the root of the extracted files, or in the directories
+/// at most `max_depth` levels below it, preferring the shallowest match. The
+/// returned path is relative to the root.
+fn find_bin(
+    extracted_files: &(impl ExtractedTree + ?Sized),
+    bin_name: &Path,
+    max_depth: usize,
+) -> Option<PathBuf> {
+    if extracted_files.is_file(bin_name) {
+        return Some(bin_name.to_path_buf());
+    }
+
+    let mut dirs = vec![PathBuf::from(".")];
+    for _ in 0..max_depth {
+        let mut subdirs = vec![];
+        for dir in dirs {
+            let Some(mut entries) = extracted_files.entries(&dir) else {
+                continue;
+            };
+            entries.sort();
+            for entry in entries {
+                let path = if dir == Path::new(".") {
+                    PathBuf::from(entry)
+                } else {
+                    dir.join(entry)
+                };
+                if extracted_files.is_dir(&path) {
+                    let candidate = path.join(bin_name);
+                    if extracted_files.is_file(&candidate) {
+                        return Some(candidate);
                     }
+                    subdirs.push(path);
                 }
+            }
+        }
+        dirs = subdirs;
+    }
 
-                stderr()
-                    .execute(Print("\n".green()))?
-                    .execute(Print(&name))?
-                    .execute(Print(" has been updated!".green()))?
-                    .execute(Print(
-                        if self.dry_run {
-                            " (not actually since it was a dry-run)"
-                        } else {
-                            ""
-                        }
-                        .dim(),
-                    ))?
-                    .execute(Print("\n"))?
-                    .execute(ResetColor)?;
-            } else {
-                return Ok(());
+    None
+}
+
+/// Calls `f` with the path of every file in `dir`, recursively.
+fn find_files(
+    extracted_files: &(impl ExtractedTree + ?Sized),
+    dir: &Path,
+    f: &mut impl FnMut(&Path),
+) {
+    let Some(entries) = extracted_files.entries(dir) else {
+        return;
+    };
+    for entry in entries {
+        let path = if dir == Path::new(".") {
+            PathBuf::from(entry)
+        } else {
+            dir.join(entry)
+        };
+        if extracted_files.is_file(&path) {
+            f(&path);
+        } else if extracted_files.is_dir(&path) {
+            find_files(extracted_files, &path, f);
+        }
+    }
+}
+
+/// Moves `bin_path` to `target_binary`, using `backup_bin` to hold the old
+/// binary while doing so.
+async fn swap(bin_path: &Path, target_binary: &Path, backup_bin: &Path) -> Result<()> {
+    swap_verifying(bin_path, target_binary, backup_bin, false).await
+}
+
+/// Like [`swap`], but if `verify_backup` is set, checks that the old binary
+/// has the same size and SHA-256 digest after being moved to `backup_bin`,
+/// moving it back and failing otherwise.
+async fn swap_verifying(
+    bin_path: &Path,
+    target_binary: &Path,
+    backup_bin: &Path,
+    verify_backup: bool,
+) -> Result<()> {
+    // NOTE: Swapping procedure:
+    // - Give the new binary the permissions of the old one, or 0o755
+    // - Move the old binary to `backup_bin`. This works on Windows even
+    //   if it is running, while removing or overwriting it does not
+    //   - If `verify_backup` is set, check that it is intact, and move it
+    //     back otherwise
+    // - Move the new binary into target destination, which
+    //   should now be vacant. Across filesystems, it is copied next to
+    //   the destination first, and then renamed into place
+    //   - If this fails, move the old binary back
+    // - The caller removes `backup_bin` afterwards, unless it is
+    //   kept to roll back to. If interrupted before the new binary
+    //   is in place, `recover` moves the old binary back
+    let had_old = tokio::fs::try_exists(target_binary).await.unwrap_or(true);
+    make_executable(bin_path, target_binary).await?;
+    if had_old {
+        let original = if verify_backup {
+            Some(size_and_digest(target_binary).await?)
+        } else {
+            None
+        };
+        move_file(target_binary, backup_bin)
+            .await
+            .map_err(|e| elevation_required(e, target_binary))
+            .wrap_err("failed to move old binary before updating to new")?;
+        if let Some(original) = original {
+            if size_and_digest(backup_bin).await.ok() != Some(original) {
+                move_file(backup_bin, target_binary)
+                    .await
+                    .wrap_err("failed to move old binary back after its backup did not match")?;
+                return Err(eyre!(
+                    "the backup of the old binary at `{}` does not match it, aborted the update",
+                    backup_bin.display()
+                ));
             }
+        }
+    }
+    if let Err(e) = move_file(bin_path, target_binary).await {
+        if !had_old {
+            return Err(elevation_required(e, target_binary))
+                .wrap_err("failed to put new binary into target destination");
+        }
+        if let Err(e2) = move_file(backup_bin, target_binary).await {
+            let error_msg = "failed to move old binary back after failing to move new binary into target destination";
+            return Err(e2).wrap_err(error_msg).wrap_err(e);
+        } else {
+            return Err(elevation_required(e, target_binary))
+                .wrap_err("failed to put new binary into target destination");
+        }
+    }
 
-            return Ok(());
+    Ok(())
+}
+
+/// Moves `from` to `to`. If they are on different filesystems, which cannot
+/// be renamed across, `from` is copied next to `to`, synced to disk, and
+/// renamed into place, such that `to` never holds a partial file.
+async fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match tokio::fs::rename(from, to).await {
+        Err(e) if is_cross_device(&e) => {}
+        res => return res,
+    }
+
+    let name = to.file_name().unwrap_or_default().to_string_lossy();
+    let sibling = to.with_file_name(format!(".{name}.binswap-tmp"));
+    let res = async {
+        tokio::fs::copy(from, &sibling).await?;
+        tokio::fs::File::open(&sibling).await?.sync_all().await?;
+        tokio::fs::rename(&sibling, to).await
+    }
+    .await;
+    if res.is_err() {
+        tokio::fs::remove_file(&sibling).await.ok();
+    }
+    res?;
+    tokio::fs::remove_file(from).await
+}
+
+/// Whether renaming failed since the paths are on different filesystems.
+fn is_cross_device(e: &io::Error) -> bool {
+    #[cfg(unix)]
+    return e.raw_os_error() == Some(libc::EXDEV);
+    #[cfg(windows)]
+    return e.raw_os_error() == Some(windows_sys::Win32::Foundation::ERROR_NOT_SAME_DEVICE as i32);
+    #[cfg(not(any(unix, windows)))]
+    return false;
+}
+
+/// Gives `bin_path` the permissions of `target_binary` if it exists, or makes
+/// it executable by everyone otherwise. Archives do not always preserve the
+/// executable bit. This does nothing on Windows.
+async fn make_executable(bin_path: &Path, target_binary: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = match tokio::fs::metadata(target_binary).await {
+            Ok(metadata) => metadata.permissions().mode() & 0o7777,
+            Err(_) => 0o755,
+        };
+        tokio::fs::set_permissions(bin_path, std::fs::Permissions::from_mode(mode))
+            .await
+            .wrap_err("failed to make the new binary executable")?;
+    }
+    #[cfg(not(unix))]
+    let _ = (bin_path, target_binary);
+
+    Ok(())
+}
+
+/// Whether the versions `a` and `b` are the same, ignoring a leading `v` and
+/// build metadata if they are semantic versions.
+fn same_version(a: &str, b: &str) -> bool {
+    let a = a.trim().trim_start_matches('v');
+    let b = b.trim().trim_start_matches('v');
+    match (semver::Version::parse(a), semver::Version::parse(b)) {
+        (Ok(a), Ok(b)) => (a.major, a.minor, a.patch, a.pre) == (b.major, b.minor, b.patch, b.pre),
+        _ => a == b,
+    }
+}
+
+/// Whether `a` is an older semantic version than `b`, ignoring a leading `v`.
+/// If either is not a semantic version, neither is considered older.
+fn is_older(a: &str, b: &str) -> bool {
+    let parse = |version: &str| semver::Version::parse(version.trim().trim_start_matches('v'));
+    match (parse(a), parse(b)) {
+        (Ok(a), Ok(b)) => (a.major, a.minor, a.patch, a.pre) < (b.major, b.minor, b.patch, b.pre),
+        _ => false,
+    }
+}
+
+/// A binary which has been swapped in, while the replaced binary is kept at
+/// `backup_bin` and the lock on `target_binary` is held.
+struct Swapped {
+    target_binary: PathBuf,
+    backup_bin: PathBuf,
+    _lock: Option<SwapLock>,
+}
+
+/// Moves the old binaries back in place of the binaries in `swapped`,
+/// removing the new binaries if there were no old ones.
+async fn roll_back_swapped(swapped: &[Swapped], temp: &Path) -> Result<()> {
+    for (i, swapped) in swapped.iter().enumerate().rev() {
+        let (destination, backup_bin) = (&swapped.target_binary, &swapped.backup_bin);
+        if tokio::fs::try_exists(backup_bin).await? {
+            swap(
+                backup_bin,
+                destination,
+                &temp.join(format!("failed-binary-{i}")),
+            )
+            .await
+        } else {
+            tokio::fs::remove_file(destination)
+                .await
+                .map_err(Into::into)
         }
+        .wrap_err_with(|| format!("failed to roll back `{}`", destination.display()))?;
+    }
+    Ok(())
+}
+
+/// Turns permission errors into [`Error::ElevationRequired`], if running with
+/// elevated privileges could resolve them.
+fn elevation_required(e: io::Error, path: &Path) -> color_eyre::Report {
+    if e.kind() == io::ErrorKind::PermissionDenied && error::can_elevate() {
+        Error::ElevationRequired {
+            path: path.to_path_buf(),
+        }
+        .into()
+    } else {
+        e.into()
+    }
+}
 
-        drop(temp);
+/// Creates `dir` and all of its missing parents, readable by everyone but only
+/// writable by the owner.
+async fn create_dir_all(dir: &Path) -> Result<()> {
+    let dir = dir.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o755);
+        builder
+            .create(&dir)
+            .wrap_err_with(|| format!("failed to create directory `{}`", dir.display()))
+    })
+    .await?
+}
 
-        Err(eyre!("not found"))
+/// Returns whether the files at `a` and `b` have the same SHA-256 digest. If
+/// `b` does not exist they are not considered identical.
+async fn is_identical(a: &Path, b: &Path) -> Result<bool> {
+    if !tokio::fs::try_exists(b).await? {
+        return Ok(false);
     }
+    Ok(sha256_file(a).await? == sha256_file(b).await?)
 }
 
-fn ask_for_confirm(stdin: &mut StdinLock, input: &mut String) -> io::Result<()> {
-    stderr()
-        .execute(Print("\n  Do you wish to continue? ".yellow()))?
-        .execute(Print("yes/[no]\n"))?
-        .execute(Print("  ? ".dim()))?
-        .execute(SavePosition)?
-        .execute(Print("\n"))?
-        .execute(RestorePosition)?;
+async fn size_and_digest(path: &Path) -> io::Result<(u64, [u8; 32])> {
+    Ok((
+        tokio::fs::metadata(path).await?.len(),
+        sha256_file(path).await?,
+    ))
+}
+
+async fn sha256_file(path: &Path) -> io::Result<[u8; 32]> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut hasher = Sha256::new();
+        io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+        Ok(hasher.finalize().into())
+    })
+    .await?
+}
+
+fn ask_for_confirm(
+    stdin: &mut StdinLock,
+    input: &mut String,
+    word: Option<&str>,
+) -> io::Result<()> {
+    let mut stderr = report::terminal();
+    match word {
+        Some(word) => stderr
+            .execute(Print("\n  Type ".yellow()))?
+            .execute(Print(word))?
+            .execute(Print(" or ".yellow()))?
+            .execute(Print("update"))?
+            .execute(Print(" to continue\n".yellow()))?,
+        None => stderr
+            .execute(Print("\n  Do you wish to continue? ".yellow()))?
+            .execute(Print("yes/[no]\n"))?,
+    }
+    .execute(Print("  ? ".dim()))?
+    .execute(SavePosition)?
+    .execute(Print("\n"))?
+    .execute(RestorePosition)?;
 
     stdin.read_line(input)?;
 
     Ok(())
 }
 
-async fn confirm() -> bool {
+/// Prompts the user for confirmation. If `word` is given, it or `update` has
+/// to be typed rather than `yes`.
+async fn confirm(word: Option<String>) -> bool {
     let (tx, rx) = oneshot::channel();
 
     thread::spawn(move || {
@@ -402,10 +2471,14 @@ async fn confirm() -> bool {
         let mut input = String::with_capacity(16);
 
         let res = loop {
-            if ask_for_confirm(&mut stdin, &mut input).is_err() {
+            if ask_for_confirm(&mut stdin, &mut input, word.as_deref()).is_err() {
                 break false;
             }
 
+            if let Some(word) = &word {
+                let answer = input.trim();
+                break answer == word || answer == "update";
+            }
             match input.as_str().trim() {
                 "yes" | "y" | "YES" | "Y" => break true,
                 "no" | "n" | "NO" | "N" | "" => break false,
@@ -423,3 +2496,113 @@ async fn confirm() -> bool {
 
     rx.await.unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn make_executable_sets_the_executable_bits() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let bin_path = dir.path().join("extracted");
+        std::fs::write(&bin_path, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&bin_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        make_executable(&bin_path, &dir.path().join("missing"))
+            .await
+            .unwrap();
+
+        let mode = std::fs::metadata(&bin_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o111, 0o111);
+    }
+
+    fn updater() -> BinswapGithub {
+        builder()
+            .repo_author("author")
+            .repo_name("repo")
+            .bin_name("tool")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn target_binary_at_installs_into_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin_name = format!("tool{}", env::consts::EXE_SUFFIX);
+        let updater = updater();
+
+        let file = dir.path().join("other");
+        assert_eq!(updater.target_binary_at(&file), file);
+
+        assert_eq!(
+            updater.target_binary_at(dir.path()),
+            dir.path().join(&bin_name)
+        );
+
+        let missing = dir.path().join("missing");
+        let with_separator = format!("{}{}", missing.display(), std::path::MAIN_SEPARATOR);
+        assert_eq!(
+            updater.target_binary_at(Path::new(&with_separator)),
+            missing.join(&bin_name)
+        );
+    }
+
+    #[test]
+    fn find_bin_finds_nested_binaries() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("pkg-1.0/bin")).unwrap();
+        std::fs::create_dir_all(dir.path().join("pkg-1.0/tool/share")).unwrap();
+        std::fs::write(dir.path().join("pkg-1.0/bin/tool"), "").unwrap();
+
+        let extracted = ExtractedDir(dir.path().to_path_buf());
+        assert_eq!(
+            find_bin(&extracted, Path::new("tool"), 4),
+            Some(PathBuf::from("pkg-1.0/bin/tool"))
+        );
+        assert_eq!(find_bin(&extracted, Path::new("tool"), 1), None);
+    }
+
+    #[test]
+    fn locate_bin_honors_the_metadata_bin_dir() {
+        use binstalk::manifests::cargo_toml_binstall::PkgMeta;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("tool-1.0.0/libexec")).unwrap();
+        std::fs::write(dir.path().join("tool-1.0.0/libexec/tool"), "").unwrap();
+        std::fs::write(dir.path().join("tool"), "").unwrap();
+
+        let target = "x86_64-unknown-linux-gnu";
+        let meta = PkgMeta {
+            bin_dir: Some("{ name }-{ version }/libexec/{ bin }{ binary-ext }".to_string()),
+            ..PkgMeta::default()
+        };
+        let extracted = ExtractedDir(dir.path().to_path_buf());
+        let locate = |updater: &BinswapGithub| {
+            let bin_dir = updater.target_meta(&meta, target).bin_dir;
+            updater.locate_bin(
+                &extracted,
+                dir.path(),
+                bin_dir.as_deref(),
+                "1.0.0",
+                target,
+                "tool",
+            )
+        };
+
+        assert_eq!(
+            locate(&updater()),
+            Some(PathBuf::from("tool-1.0.0/libexec/tool"))
+        );
+        let overridden = builder()
+            .repo_author("author")
+            .repo_name("repo")
+            .bin_name("tool")
+            .bin_dir("{ bin }")
+            .build()
+            .unwrap();
+        assert_eq!(locate(&overridden), Some(PathBuf::from("tool")));
+    }
+}
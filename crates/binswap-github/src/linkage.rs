@@ -0,0 +1,204 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use color_eyre::Result;
+
+use crate::{
+    host::{elf_program_headers, macho_load_commands},
+    BinswapGithub, Error,
+};
+
+const PT_INTERP: u32 = 3;
+const LC_LOAD_DYLIB: u32 = 0xc;
+/// The maximum length of the name of an imported DLL which is read.
+const NAME_MAX: usize = 256;
+
+/// The library every macOS binary links against, since the system call
+/// interface is not stable.
+const LIB_SYSTEM: &str = "/usr/lib/libSystem.B.dylib";
+/// The prefixes of the DLLs of runtimes which can be linked statically into
+/// Windows binaries. System DLLs like `kernel32.dll` are always linked
+/// dynamically.
+const RUNTIME_DLL_PREFIXES: &[&str] = &[
+    "vcruntime",
+    "msvcp",
+    "ucrtbase",
+    "api-ms-win-crt-",
+    "libgcc_s",
+    "libstdc++",
+    "libwinpthread",
+];
+
+impl BinswapGithub {
+    /// Checks that the downloaded binary is statically linked, if required.
+    pub(crate) fn check_static(&self, bin_path: &Path) -> Result<()> {
+        if !self.require_static {
+            return Ok(());
+        }
+
+        match dynamic_dependency(bin_path) {
+            Some(dependency) => Err(Error::NotStaticallyLinked { dependency }.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A dynamic dependency of the binary at `path`, if it has any.
+fn dynamic_dependency(path: &Path) -> Option<String> {
+    if let Some(headers) = elf_program_headers(path) {
+        return headers
+            .iter()
+            .any(|header| header.kind == PT_INTERP)
+            .then(|| "the dynamic loader".to_string());
+    }
+
+    if let Some(slices) = macho_load_commands(path) {
+        return slices.iter().flatten().find_map(|command| {
+            if command.kind != LC_LOAD_DYLIB {
+                return None;
+            }
+            let name_offset = u32::from_le_bytes(command.data.get(8..12)?.try_into().ok()?);
+            let name = command.data.get(name_offset as usize..)?;
+            let name = String::from_utf8_lossy(name.split(|&b| b == 0).next()?);
+            (name != LIB_SYSTEM).then(|| name.into_owned())
+        });
+    }
+
+    pe_imports(path)?.into_iter().find(|dll| {
+        let dll = dll.to_lowercase();
+        RUNTIME_DLL_PREFIXES
+            .iter()
+            .any(|prefix| dll.starts_with(prefix))
+    })
+}
+
+/// The names of the DLLs imported by the PE file at `path`, or `None` if it
+/// is not a PE file.
+fn pe_imports(path: &Path) -> Option<Vec<String>> {
+    let mut file = File::open(path).ok()?;
+    // Reads `len` bytes at `offset`, or less if the file ends before.
+    let mut read_at = |offset: u64, len: usize| -> Option<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(len);
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        (&mut file).take(len as u64).read_to_end(&mut bytes).ok()?;
+        (bytes.len() == len || len == NAME_MAX).then_some(bytes)
+    };
+    let u16_at = |bytes: &[u8], offset: usize| -> Option<u16> {
+        Some(u16::from_le_bytes(
+            bytes.get(offset..offset + 2)?.try_into().ok()?,
+        ))
+    };
+    let u32_at = |bytes: &[u8], offset: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            bytes.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+
+    let dos_header = read_at(0, 64)?;
+    if &dos_header[..2] != b"MZ" {
+        return None;
+    }
+    let pe_offset = u64::from(u32_at(&dos_header, 0x3c)?);
+    let headers = read_at(pe_offset, 24)?;
+    if &headers[..4] != b"PE\0\0" {
+        return None;
+    }
+    let section_count = usize::from(u16_at(&headers, 6)?);
+    let optional_header_size = usize::from(u16_at(&headers, 20)?);
+    let optional_header = read_at(pe_offset + 24, optional_header_size)?;
+    let data_directories = match u16_at(&optional_header, 0)? {
+        0x10b => 96,
+        0x20b => 112,
+        _ => return None,
+    };
+    // The import table is the second data directory.
+    let import_rva = u32_at(&optional_header, data_directories + 8)?;
+    if import_rva == 0 {
+        return Some(vec![]);
+    }
+
+    let sections = read_at(
+        pe_offset + 24 + optional_header_size as u64,
+        40 * section_count,
+    )?;
+    let file_offset = |rva: u32| -> Option<u64> {
+        sections.chunks_exact(40).find_map(|section| {
+            let size = u32_at(section, 8)?.max(u32_at(section, 16)?);
+            let address = u32_at(section, 12)?;
+            let raw_offset = u32_at(section, 20)?;
+            (address..address.checked_add(size)?)
+                .contains(&rva)
+                .then(|| u64::from(rva - address) + u64::from(raw_offset))
+        })
+    };
+
+    let mut imports = vec![];
+    let mut descriptor_offset = file_offset(import_rva)?;
+    loop {
+        let descriptor = read_at(descriptor_offset, 20)?;
+        let name_rva = u32_at(&descriptor, 12)?;
+        if name_rva == 0 {
+            break;
+        }
+        let name = read_at(file_offset(name_rva)?, NAME_MAX)?;
+        let name = name.split(|&b| b == 0).next()?;
+        imports.push(String::from_utf8_lossy(name).into_owned());
+        descriptor_offset += 20;
+    }
+    Some(imports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A PE32+ file importing `VCRUNTIME140.dll`, with a single section
+    /// mapping the virtual address `0x1000` to the file offset
+    /// `raw_offset`. The import table is written for it to be `0x200`.
+    fn pe(raw_offset: u32) -> tempfile::NamedTempFile {
+        let mut bytes = vec![0; 0x300];
+        bytes[..2].copy_from_slice(b"MZ");
+        bytes[0x3c..0x40].copy_from_slice(&64u32.to_le_bytes());
+        bytes[64..68].copy_from_slice(b"PE\0\0");
+        bytes[70..72].copy_from_slice(&1u16.to_le_bytes());
+        bytes[84..86].copy_from_slice(&240u16.to_le_bytes());
+        // The optional header, with the import table at `0x1080`.
+        bytes[88..90].copy_from_slice(&0x20bu16.to_le_bytes());
+        bytes[88 + 120..88 + 124].copy_from_slice(&0x1080u32.to_le_bytes());
+        // The section table.
+        let section = 88 + 240;
+        bytes[section + 8..section + 12].copy_from_slice(&0x100u32.to_le_bytes());
+        bytes[section + 12..section + 16].copy_from_slice(&0x1000u32.to_le_bytes());
+        bytes[section + 16..section + 20].copy_from_slice(&0x100u32.to_le_bytes());
+        bytes[section + 20..section + 24].copy_from_slice(&raw_offset.to_le_bytes());
+        // The import descriptors, naming the DLL at `0x10c0`.
+        bytes[0x28c..0x290].copy_from_slice(&0x10c0u32.to_le_bytes());
+        bytes[0x2c0..0x2d0].copy_from_slice(b"VCRUNTIME140.dll");
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), bytes).unwrap();
+        file
+    }
+
+    #[test]
+    fn pe_imports_are_read() {
+        let file = pe(0x200);
+        assert_eq!(
+            pe_imports(file.path()),
+            Some(vec!["VCRUNTIME140.dll".to_string()])
+        );
+        assert_eq!(
+            dynamic_dependency(file.path()).as_deref(),
+            Some("VCRUNTIME140.dll")
+        );
+    }
+
+    #[test]
+    fn pe_imports_handle_sections_at_the_end_of_the_address_space() {
+        let file = pe(0xffff_ff80);
+        assert_eq!(pe_imports(file.path()), None);
+    }
+}
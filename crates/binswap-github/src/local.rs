@@ -0,0 +1,254 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use binstalk::manifests::cargo_toml_binstall::PkgFmt;
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+
+use crate::{
+    asset::mapped_pkg_fmt, file_name, BinswapGithub, ExtractedDir, Fetched, ProgressEvent,
+    SwapEvent, SwapOutcome,
+};
+
+impl BinswapGithub {
+    /// Installs the binary from `archive`, a release asset for `target`
+    /// which has already been downloaded, in place of the currently executed
+    /// binary, see [`install_from_archive_to`](Self::install_from_archive_to).
+    pub async fn install_from_archive(
+        &self,
+        archive: impl AsRef<Path>,
+        target: &str,
+    ) -> Result<SwapOutcome> {
+        let mut this = self.clone();
+        this.in_place = true;
+        this.install_from_archive_to(archive, target, self.current_exe()?)
+            .await
+    }
+
+    /// Installs the binary from `archive`, a release asset for `target`
+    /// which has already been downloaded, to `target_binary`, without
+    /// reaching the network. This is useful in air-gapped environments or
+    /// behind a mirror.
+    ///
+    /// The version is not resolved, but taken from the file name of the
+    /// archive, if it contains one. Its format is determined like that of
+    /// downloaded assets, and it is extracted and searched for `bin_name`
+    /// like them. The binary is then checked and written like a downloaded
+    /// one, honoring `dry_run`, `no_confirm` and the check command.
+    pub async fn install_from_archive_to(
+        &self,
+        archive: impl AsRef<Path>,
+        target: &str,
+        target_binary: impl AsRef<Path>,
+    ) -> Result<SwapOutcome> {
+        let target_binary = self.target_binary_at(target_binary.as_ref());
+        let outcome = self
+            .measured(
+                |this| async move {
+                    this.archive_to(archive.as_ref(), target, &target_binary)
+                        .await
+                },
+                |outcome| (outcome.version.clone(), outcome.status.clone()),
+            )
+            .await?;
+        self.print_json_event(&SwapEvent::Done(outcome.clone()));
+        Ok(outcome)
+    }
+    async fn archive_to(
+        &self,
+        archive: &Path,
+        target: &str,
+        target_binary: &Path,
+    ) -> Result<SwapOutcome> {
+        self.print_updating(file_name(target_binary)?)?;
+
+        let temp = self.scratch_dir()?;
+        if !self.dry_run {
+            self.recover(target_binary).await?;
+        }
+
+        let archive_name = file_name(archive)?;
+        let pkg_fmt = self
+            .pkg_fmt
+            .or_else(|| mapped_pkg_fmt(&self.extension_format_map, archive_name))
+            .or_else(|| PkgFmt::guess_pkg_format(archive_name))
+            .unwrap_or(PkgFmt::Bin);
+        let version = version_in_name(archive_name).unwrap_or_default();
+
+        let extracted_files = self
+            .extract_asset(archive, pkg_fmt, target, temp.path())
+            .await?;
+        let found = self
+            .locate_bin(
+                &extracted_files,
+                temp.path(),
+                self.bin_dir.as_deref(),
+                &version,
+                target,
+                &self.bin_name,
+            )
+            .ok_or_else(|| {
+                eyre!(
+                    "`{}` does not contain a `{}` which runs on this host",
+                    archive.display(),
+                    self.bin_file_name(target)
+                )
+            })?;
+
+        let fetched = Fetched {
+            version,
+            build_id: None,
+            target: target.to_string(),
+            found,
+            download_bytes: 0,
+            download_duration: None,
+        };
+        let client = self.client()?;
+        self.install_fetched(&client, None, fetched, temp.path(), target_binary)
+            .await
+    }
+
+    /// Extracts `archive`, a downloaded asset for `target` of the format
+    /// `pkg_fmt`, into `dir`.
+    pub(crate) async fn extract_asset(
+        &self,
+        archive: &Path,
+        pkg_fmt: PkgFmt,
+        target: &str,
+        dir: &Path,
+    ) -> Result<ExtractedDir> {
+        self.progress(ProgressEvent::Extracting);
+        let name = file_name(archive)?;
+        let (path, to, bin) = (
+            archive.to_path_buf(),
+            dir.to_path_buf(),
+            self.bin_file_name(target),
+        );
+        tokio::task::spawn_blocking(move || extract(&path, pkg_fmt, &to, &bin))
+            .await?
+            .wrap_err_with(|| format!("failed to extract `{name}`"))?;
+        Ok(ExtractedDir(dir.to_path_buf()))
+    }
+
+    /// The file name of `bin_name` in assets for `target`.
+    pub(crate) fn bin_file_name(&self, target: &str) -> String {
+        if target.contains("windows") {
+            format!("{}.exe", self.bin_name)
+        } else {
+            self.bin_name.clone()
+        }
+    }
+}
+
+/// Extracts `archive` of the format `pkg_fmt` into `dir`. A bare binary is
+/// copied into it as `bin_name`.
+pub(crate) fn extract(archive: &Path, pkg_fmt: PkgFmt, dir: &Path, bin_name: &str) -> Result<()> {
+    match pkg_fmt {
+        PkgFmt::Bin => {
+            fs::create_dir_all(dir)?;
+            fs::copy(archive, dir.join(bin_name))?;
+            Ok(())
+        }
+        PkgFmt::Zip => {
+            zip::ZipArchive::new(BufReader::new(File::open(archive)?))?.extract(dir)?;
+            Ok(())
+        }
+        _ => extract_tar(archive, pkg_fmt, dir),
+    }
+}
+
+/// Extracts the tar based `archive` of the format `pkg_fmt` into `dir`.
+pub(crate) fn extract_tar(archive: &Path, pkg_fmt: PkgFmt, dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let file = BufReader::new(File::open(archive)?);
+    let reader: Box<dyn Read> = match pkg_fmt {
+        PkgFmt::Bin | PkgFmt::Zip => {
+            return Err(eyre!("`{}` is not a tar based archive", archive.display()))
+        }
+        PkgFmt::Tar => Box::new(file),
+        PkgFmt::Tbz2 => Box::new(bzip2::read::BzDecoder::new(file)),
+        PkgFmt::Tgz => Box::new(flate2::read::GzDecoder::new(file)),
+        PkgFmt::Txz => Box::new(xz2::read::XzDecoder::new(file)),
+        PkgFmt::Tzstd => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+    };
+    tar::Archive::new(reader).unpack(dir)?;
+    Ok(())
+}
+
+/// The version in the file name of an archive, such as `1.2.3` in
+/// `tool-v1.2.3-x86_64-unknown-linux-gnu.tar.gz`.
+fn version_in_name(name: &str) -> Option<String> {
+    name.split(['-', '_'])
+        .map(|part| part.trim_start_matches('v'))
+        .find(|part| part.starts_with(|c: char| c.is_ascii_digit()) && part.contains('.'))
+        .map(|part| {
+            let end = part
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(part.len());
+            part[..end].trim_end_matches('.').to_string()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    /// Writes a zip archive at `archive` holding `contents` as
+    /// `tool-1.0.0/tool`.
+    fn write_zip(archive: &Path, contents: &[u8]) {
+        let mut zip = zip::ZipWriter::new(File::create(archive).unwrap());
+        let options = zip::write::FileOptions::default();
+        zip.start_file("tool-1.0.0/tool", options).unwrap();
+        zip.write_all(contents).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn extract_unpacks_zip_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("tool.zip");
+        write_zip(&archive, b"binary");
+
+        let out = dir.path().join("out");
+        extract(&archive, PkgFmt::Zip, &out, "tool").unwrap();
+        assert_eq!(fs::read(out.join("tool-1.0.0/tool")).unwrap(), b"binary");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn install_from_archive_to_installs_from_zip_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("tool-v1.0.0-x86_64-unknown-linux-gnu.zip");
+        write_zip(&archive, b"#!/bin/sh\necho 1.0.0\n");
+        let metrics = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let reported = std::sync::Arc::clone(&metrics);
+        let updater = crate::builder()
+            .repo_author("author")
+            .repo_name("repo")
+            .bin_name("tool")
+            .no_confirm(true)
+            .quiet(true)
+            .on_metrics(move |metrics| reported.lock().unwrap().push(metrics))
+            .build()
+            .unwrap();
+
+        let target_binary = dir.path().join("installed");
+        let outcome = updater
+            .install_from_archive_to(&archive, "x86_64-unknown-linux-gnu", &target_binary)
+            .await
+            .unwrap();
+        assert_eq!(outcome.version, "1.0.0");
+        assert_eq!(
+            fs::read(&target_binary).unwrap(),
+            b"#!/bin/sh\necho 1.0.0\n"
+        );
+        assert_eq!(metrics.lock().unwrap().len(), 1);
+    }
+}
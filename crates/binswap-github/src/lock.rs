@@ -0,0 +1,177 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use color_eyre::{eyre::Context, Result};
+use fs4::FileExt;
+use sha2::{Digest, Sha256};
+
+use crate::{is_identical, same_version, BinswapGithub, Error};
+
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An exclusive lock on a target path held across processes. It is released
+/// when dropped, removing the lock file while it is still held.
+pub(crate) struct SwapLock {
+    file: File,
+    path: PathBuf,
+    /// Whether another process held the lock when it was first tried.
+    pub(crate) waited: bool,
+}
+
+impl BinswapGithub {
+    /// Takes the lock for swapping `target_binary`, waiting at most
+    /// `lock_timeout` for other processes holding it. Returns `None` if
+    /// locking is disabled.
+    pub(crate) async fn lock(&self, target_binary: &Path) -> Result<Option<SwapLock>> {
+        if self.no_lock {
+            return Ok(None);
+        }
+
+        let lock_path = lock_path(target_binary)?;
+        let started = Instant::now();
+        let mut waited = false;
+        loop {
+            let file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(false)
+                .open(&lock_path)
+                .wrap_err_with(|| format!("failed to open lock file `{}`", lock_path.display()))?;
+            match file.try_lock_exclusive() {
+                // The holder removes the lock file before releasing it, so
+                // the file locked here may no longer be the one at the path.
+                Ok(()) if is_current(&file, &lock_path) => {
+                    return Ok(Some(SwapLock {
+                        file,
+                        path: lock_path,
+                        waited,
+                    }))
+                }
+                Ok(()) => {
+                    waited = true;
+                    continue;
+                }
+                Err(e) if e.kind() == fs4::lock_contended_error().kind() => waited = true,
+                Err(e) => return Err(e).wrap_err("failed to lock target binary"),
+            }
+            if started.elapsed() >= self.lock_timeout {
+                return Err(Error::LockTimeout {
+                    path: target_binary.to_path_buf(),
+                    timeout: self.lock_timeout,
+                }
+                .into());
+            }
+            tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Whether `target_binary` has been updated to `version` by another
+    /// process while waiting for the lock, which is the case if it is now
+    /// identical to the new binary at `bin_path`, or
+    /// `detect_current_version` detects `version`.
+    pub(crate) async fn updated_while_waiting(
+        &self,
+        lock: Option<&SwapLock>,
+        bin_path: &Path,
+        target_binary: &Path,
+        version: &str,
+    ) -> Result<bool> {
+        if !matches!(lock, Some(lock) if lock.waited) {
+            return Ok(false);
+        }
+        if is_identical(bin_path, target_binary).await? {
+            return Ok(true);
+        }
+        let installed = self
+            .detect_current_version
+            .as_ref()
+            .and_then(|detect| detect(target_binary));
+        Ok(matches!(installed, Some(installed) if same_version(&installed, version)))
+    }
+}
+
+impl Drop for SwapLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = self.file.unlock();
+    }
+}
+
+/// Whether the locked `file` is still the lock file at `path`.
+#[cfg(unix)]
+fn is_current(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(file), Ok(path)) => file.dev() == path.dev() && file.ino() == path.ino(),
+        _ => false,
+    }
+}
+
+/// Whether the locked `file` is still the lock file at `path`. Open files
+/// cannot be removed here, so it always is.
+#[cfg(not(unix))]
+fn is_current(_file: &File, _path: &Path) -> bool {
+    true
+}
+
+/// The lock file for `target_binary`. It is kept in the temporary directory,
+/// named after the digest of the absolute target path, with its directory
+/// canonicalized, so that processes writing the same target agree on it
+/// regardless of symlinks and relative components.
+fn lock_path(target_binary: &Path) -> io::Result<PathBuf> {
+    let absolute = env::current_dir()?.join(target_binary);
+    let absolute = match (absolute.parent(), absolute.file_name()) {
+        (Some(parent), Some(name)) => match parent.canonicalize() {
+            Ok(parent) => parent.join(name),
+            Err(_) => absolute,
+        },
+        _ => absolute,
+    };
+    let digest = Sha256::digest(absolute.to_string_lossy().as_bytes());
+    let name: String = digest.iter().map(|b| format!("{b:02x}")).collect();
+    Ok(env::temp_dir().join(format!("binswap-{name}.lock")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_path_is_the_same_through_relative_components() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+
+        assert_eq!(
+            lock_path(&dir.path().join("sub/../tool")).unwrap(),
+            lock_path(&dir.path().join("tool")).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn lock_file_is_removed_when_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("tool");
+        let binswap = crate::builder()
+            .repo_author("author")
+            .repo_name("repo")
+            .bin_name("tool")
+            .build()
+            .unwrap();
+
+        let lock = binswap.lock(&target).await.unwrap().unwrap();
+        assert!(lock.path.exists());
+        assert!(!lock.waited);
+        let path = lock.path.clone();
+        drop(lock);
+        assert!(!path.exists());
+
+        let lock = binswap.lock(&target).await.unwrap().unwrap();
+        assert!(!lock.waited);
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    future::Future,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use color_eyre::Result;
+
+use crate::{find_files, BinswapGithub, ExtractedTree, SwapStatus};
+
+/// Measurements of a single update, passed to the
+/// [`on_metrics`](crate::BinswapGithubBuilder::on_metrics) hook once it has
+/// finished.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct UpdateMetrics {
+    /// How long it took to resolve the version to install, if it got that
+    /// far.
+    pub resolve_duration: Option<Duration>,
+    /// How long it took to download and extract the release assets, if any
+    /// were downloaded.
+    pub download_duration: Option<Duration>,
+    /// The total size of the files extracted from the downloaded assets in
+    /// bytes.
+    pub download_bytes: u64,
+    /// How long the whole update took.
+    pub total_duration: Duration,
+    /// How many times a request to GitHub was retried.
+    pub retries: u32,
+    /// The version which was resolved, if the update succeeded.
+    pub version: Option<String>,
+    /// The status of the update, or `None` if it failed.
+    pub status: Option<SwapStatus>,
+}
+
+impl BinswapGithub {
+    /// Runs `run` on a copy of the configuration which records metrics, and
+    /// reports them to `on_metrics`, if set.
+    pub(crate) async fn measured<T, Fut>(
+        &self,
+        run: impl FnOnce(BinswapGithub) -> Fut,
+        summary: impl FnOnce(&T) -> (String, SwapStatus),
+    ) -> Result<T>
+    where
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut this = self.fresh_budget();
+        let Some(on_metrics) = &self.on_metrics else {
+            return run(this).await;
+        };
+
+        let metrics = Arc::new(Mutex::new(UpdateMetrics::default()));
+        this.metrics = Some(Arc::clone(&metrics));
+
+        let started = Instant::now();
+        let res = run(this).await;
+
+        let mut metrics = metrics.lock().unwrap().clone();
+        metrics.total_duration = started.elapsed();
+        if let Ok(outcome) = &res {
+            let (version, status) = summary(outcome);
+            metrics.version = Some(version);
+            metrics.status = Some(status);
+        }
+        on_metrics(metrics);
+
+        res
+    }
+
+    /// Updates the metrics of the current update, if they are recorded.
+    pub(crate) fn record(&self, f: impl FnOnce(&mut UpdateMetrics)) {
+        if let Some(metrics) = &self.metrics {
+            f(&mut metrics.lock().unwrap());
+        }
+    }
+
+    /// Records a download which took `duration` and extracted `bytes`.
+    pub(crate) fn record_download(&self, duration: Duration, bytes: u64) {
+        self.record(|metrics| {
+            *metrics.download_duration.get_or_insert(Duration::ZERO) += duration;
+            metrics.download_bytes += bytes;
+        });
+    }
+}
+
+/// The total size of the `extracted_files` in `dir` in bytes.
+pub(crate) async fn extracted_size(dir: &Path, extracted_files: &dyn ExtractedTree) -> u64 {
+    let mut paths = vec![];
+    find_files(extracted_files, Path::new("."), &mut |path| {
+        paths.push(dir.join(path))
+    });
+    let mut bytes = 0;
+    for path in paths {
+        if let Ok(metadata) = tokio::fs::metadata(path).await {
+            bytes += metadata.len();
+        }
+    }
+    bytes
+}
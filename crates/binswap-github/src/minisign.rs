@@ -0,0 +1,256 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use binstalk::helpers::remote::Client;
+use blake2::{Blake2b512, Digest};
+use color_eyre::{eyre::eyre, Result};
+use ring::signature::{UnparsedPublicKey, ED25519};
+
+use crate::{sidecar::find_sidecar, AssetInfo, BinswapGithub, Error, ReleaseInfo};
+
+/// The suffixes of minisign signatures of an asset.
+const SIGNATURE_SUFFIXES: &[&str] = &[".minisig", ".sig"];
+
+/// A minisign public key.
+struct PublicKey {
+    key_id: [u8; 8],
+    key: [u8; 32],
+}
+
+impl PublicKey {
+    /// Parses the key from the contents of a `.pub` file, or just its
+    /// base64 encoded line.
+    fn parse(s: &str) -> Result<PublicKey> {
+        let line = s
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+            .ok_or_else(|| eyre!("the public key is empty"))?;
+        let bytes = STANDARD.decode(line)?;
+        if bytes.len() != 42 || &bytes[..2] != b"Ed" {
+            return Err(eyre!("the public key is not a minisign Ed25519 key"));
+        }
+        Ok(PublicKey {
+            key_id: bytes[2..10].try_into().unwrap(),
+            key: bytes[10..].try_into().unwrap(),
+        })
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        UnparsedPublicKey::new(&ED25519, self.key)
+            .verify(message, signature)
+            .is_ok()
+    }
+}
+
+/// A minisign signature, as found in a `.minisig` file.
+struct Signature {
+    prehashed: bool,
+    key_id: [u8; 8],
+    signature: [u8; 64],
+    trusted_comment: String,
+    global_signature: [u8; 64],
+}
+
+impl Signature {
+    fn parse(s: &str) -> Result<Signature> {
+        let mut lines = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("untrusted comment:"));
+        let mut next = || {
+            lines
+                .next()
+                .ok_or_else(|| eyre!("the signature is truncated"))
+        };
+
+        let bytes = STANDARD.decode(next()?)?;
+        let prehashed = match bytes.get(..2) {
+            Some(b"Ed") => false,
+            Some(b"ED") => true,
+            _ => return Err(eyre!("the signature is not a minisign Ed25519 signature")),
+        };
+        if bytes.len() != 74 {
+            return Err(eyre!("the signature has an invalid length"));
+        }
+        let trusted_comment = next()?
+            .strip_prefix("trusted comment: ")
+            .ok_or_else(|| eyre!("the signature has no trusted comment"))?
+            .to_string();
+        let global_signature: [u8; 64] = STANDARD
+            .decode(next()?)?
+            .try_into()
+            .map_err(|_| eyre!("the global signature has an invalid length"))?;
+
+        Ok(Signature {
+            prehashed,
+            key_id: bytes[2..10].try_into().unwrap(),
+            signature: bytes[10..].try_into().unwrap(),
+            trusted_comment,
+            global_signature,
+        })
+    }
+}
+
+/// Verifies `data` against the minisign `signature` made with `pub_key`,
+/// returning why it is not valid if it is not. Prehashed signatures are
+/// checked while streaming `data`, while legacy ones need it all in memory.
+fn verify(
+    pub_key: &str,
+    signature: &str,
+    mut data: impl Read,
+) -> io::Result<std::result::Result<(), String>> {
+    let pub_key = match PublicKey::parse(pub_key) {
+        Ok(pub_key) => pub_key,
+        Err(e) => return Ok(Err(format!("invalid public key: {e}"))),
+    };
+    let sig = match Signature::parse(signature) {
+        Ok(sig) => sig,
+        Err(e) => return Ok(Err(format!("invalid signature: {e}"))),
+    };
+
+    if sig.key_id != pub_key.key_id {
+        return Ok(Err("it was made with a different key".to_string()));
+    }
+    let message = if sig.prehashed {
+        let mut hasher = Blake2b512::new();
+        io::copy(&mut data, &mut hasher)?;
+        hasher.finalize().to_vec()
+    } else {
+        let mut message = vec![];
+        data.read_to_end(&mut message)?;
+        message
+    };
+    if !pub_key.verify(&message, &sig.signature) {
+        return Ok(Err("the signature does not match".to_string()));
+    }
+    let mut global = sig.signature.to_vec();
+    global.extend_from_slice(sig.trusted_comment.as_bytes());
+    if !pub_key.verify(&global, &sig.global_signature) {
+        return Ok(Err("the trusted comment does not match".to_string()));
+    }
+    Ok(Ok(()))
+}
+
+impl BinswapGithub {
+    /// Verifies the downloaded `asset`, which is at `path`, against its
+    /// minisign signature in the release, if `pub_key` is set. A missing
+    /// or bad signature fails with [`Error::BadSignature`].
+    pub(crate) async fn verify_signature(
+        &self,
+        client: &Client,
+        release: &ReleaseInfo,
+        asset: &AssetInfo,
+        path: &Path,
+    ) -> Result<()> {
+        let Some(pub_key) = &self.pub_key else {
+            return Ok(());
+        };
+
+        let bad = |reason: &str| {
+            Error::BadSignature {
+                asset: asset.name.clone(),
+                reason: reason.to_string(),
+            }
+            .into()
+        };
+        let signature = find_sidecar(release, asset, SIGNATURE_SUFFIXES);
+        let Some(signature) = self.fetch_sidecar(client, signature).await? else {
+            return Err(bad("the release has no signature for it"));
+        };
+
+        let (pub_key, path) = (pub_key.clone(), path.to_path_buf());
+        let signature = String::from_utf8_lossy(&signature.bytes).into_owned();
+        tokio::task::spawn_blocking(move || {
+            verify(&pub_key, &signature, BufReader::new(File::open(path)?))
+        })
+        .await??
+        .map_err(|reason| bad(&reason))
+    }
+}
+
+/// Whether `release` has a minisign signature for `asset`.
+pub(crate) fn has_signature(release: &ReleaseInfo, asset: &AssetInfo) -> bool {
+    find_sidecar(release, asset, SIGNATURE_SUFFIXES).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A key and a prehashed signature of [`ASSET`] made with it by rsign.
+    const PUB_KEY: &str = "untrusted comment: minisign public key: 26689CEF5CC1C277
+RWR3wsFc75xoJvVC1eoJ0b2VX/Il3Nr6AEE7GaDAvXfoQZI9kNhZvzlD
+";
+    const OTHER_PUB_KEY: &str = "RWTmlVxQpX7uw6zecZzviRR8kkivto+WOAMoLAVuxVVMJq58c8iMND21";
+    const SIGNATURE: &str = "untrusted comment: signature from rsign secret key
+RUR3wsFc75xoJtIc8SjMa1KEOpLxjuZtE53j2N7duBzqxk49DD4+esekiraRaa0QPQzrf/DGmRfmaqa+XvlYabxAOZB6ylGHggY=
+trusted comment: timestamp:0 file:asset
+1BA85XtmtuNjX6n8NqGZsn61yjE8e6M78ST8tAxnikvwcHBUUhN5uyiIQQbCxMRKsoKu91zW7limZ3gO6nXzAg==
+";
+    const ASSET: &[u8] = b"binswap test asset\n";
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    #[test]
+    fn prehash_matches_rfc_7693() {
+        assert_eq!(
+            hex(&Blake2b512::digest(b"")),
+            "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419\
+             d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce"
+        );
+        assert_eq!(
+            hex(&Blake2b512::digest(b"abc")),
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+             7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+    }
+
+    #[test]
+    fn verifies_prehashed_signatures() {
+        assert_eq!(verify(PUB_KEY, SIGNATURE, ASSET).unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_other_data() {
+        assert_eq!(
+            verify(PUB_KEY, SIGNATURE, &b"binswap test asset, tampered\n"[..]).unwrap(),
+            Err("the signature does not match".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_signatures_made_with_other_keys() {
+        assert_eq!(
+            verify(OTHER_PUB_KEY, SIGNATURE, ASSET).unwrap(),
+            Err("it was made with a different key".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_trusted_comments() {
+        let signature = SIGNATURE.replace("file:asset", "file:other");
+        assert_eq!(
+            verify(PUB_KEY, &signature, ASSET).unwrap(),
+            Err("the trusted comment does not match".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_base64() {
+        let signature = SIGNATURE.replace("RUR3", "RUR3!");
+        let reason = verify(PUB_KEY, &signature, ASSET).unwrap().unwrap_err();
+        assert!(reason.starts_with("invalid signature:"), "{reason}");
+
+        let reason = verify("RWR3wsFc75xo!", SIGNATURE, ASSET)
+            .unwrap()
+            .unwrap_err();
+        assert!(reason.starts_with("invalid public key:"), "{reason}");
+    }
+}
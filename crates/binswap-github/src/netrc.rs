@@ -0,0 +1,157 @@
+use std::{env, fs, path::PathBuf};
+
+/// The token from the `.netrc` file, which is the password of the entry for
+/// the first of `hosts` with one, or of the `default` entry if none has one.
+/// The file is located by `NETRC`, or is in the home directory.
+pub(crate) fn netrc_token(hosts: &[String]) -> Option<String> {
+    let contents = fs::read_to_string(netrc_path()?).ok()?;
+    token(&parse(&contents), hosts)
+}
+
+fn token(entries: &[Entry], hosts: &[String]) -> Option<String> {
+    hosts
+        .iter()
+        .find_map(|host| {
+            entries
+                .iter()
+                .find(|entry| entry.machine.as_ref() == Some(host))
+                .and_then(|entry| entry.password.clone())
+        })
+        .or_else(|| {
+            entries
+                .iter()
+                .find(|entry| entry.machine.is_none())
+                .and_then(|entry| entry.password.clone())
+        })
+}
+
+fn netrc_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("NETRC") {
+        return Some(PathBuf::from(path));
+    }
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    let name = if cfg!(windows) { "_netrc" } else { ".netrc" };
+    Some(PathBuf::from(home).join(name))
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Entry {
+    /// The host of the entry, or `None` for the `default` entry.
+    machine: Option<String>,
+    password: Option<String>,
+}
+
+fn parse(contents: &str) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = vec![];
+    // The keyword whose value is the next token, which may be on a later
+    // line.
+    let mut keyword = None;
+    let mut lines = contents.lines();
+    while let Some(line) = lines.next() {
+        for token in line.split_whitespace() {
+            match keyword.take() {
+                Some("machine") => entries.push(Entry {
+                    machine: Some(token.to_string()),
+                    password: None,
+                }),
+                Some("password") => {
+                    if let Some(entry) = entries.last_mut() {
+                        entry.password = Some(token.to_string());
+                    }
+                }
+                Some(_) => {}
+                None => match token {
+                    "machine" | "password" | "login" | "account" => keyword = Some(token),
+                    "default" => entries.push(Entry::default()),
+                    // Macros run until the next empty line.
+                    "macdef" => {
+                        for line in lines.by_ref() {
+                            if line.trim().is_empty() {
+                                break;
+                            }
+                        }
+                        break;
+                    }
+                    _ if token.starts_with('#') => break,
+                    _ => {}
+                },
+            }
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(machine: Option<&str>, password: Option<&str>) -> Entry {
+        Entry {
+            machine: machine.map(String::from),
+            password: password.map(String::from),
+        }
+    }
+
+    #[test]
+    fn parses_several_entries() {
+        let entries = parse(
+            "machine github.com login me password one\n\
+             machine gitlab.com login me password two\n",
+        );
+        assert_eq!(
+            entries,
+            [
+                entry(Some("github.com"), Some("one")),
+                entry(Some("gitlab.com"), Some("two")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_tokens_on_separate_lines() {
+        let entries = parse("machine\n  github.com\n  login me\n  password\n  secret\n");
+        assert_eq!(entries, [entry(Some("github.com"), Some("secret"))]);
+    }
+
+    #[test]
+    fn skips_comments() {
+        let entries = parse(
+            "# machine example.com password nope\n\
+             machine github.com # password nope\n\
+             password secret\n",
+        );
+        assert_eq!(entries, [entry(Some("github.com"), Some("secret"))]);
+    }
+
+    #[test]
+    fn skips_macros() {
+        let entries = parse(
+            "machine github.com password one\n\
+             macdef init\n\
+             machine example.com password nope\n\
+             \n\
+             machine gitlab.com password two\n",
+        );
+        assert_eq!(
+            entries,
+            [
+                entry(Some("github.com"), Some("one")),
+                entry(Some("gitlab.com"), Some("two")),
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_default_entry() {
+        let entries = parse("machine github.com password one\ndefault login me password any\n");
+        let hosts = |hosts: &[&str]| hosts.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            token(&entries, &hosts(&["api.github.com", "github.com"])).as_deref(),
+            Some("one")
+        );
+        assert_eq!(
+            token(&entries, &hosts(&["gitlab.com"])).as_deref(),
+            Some("any")
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::{fmt, path::PathBuf, time::Duration};
+
+/// The result of [`fetch_and_write_to`](crate::BinswapGithub::fetch_and_write_to)
+/// and [`fetch_and_write_in_place_of_current_exec`](crate::BinswapGithub::fetch_and_write_in_place_of_current_exec).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SwapOutcome {
+    /// The version of the release the binary was taken from.
+    pub version: String,
+    /// Identifies the build of the release, which changes when its assets
+    /// are updated. This is the newest update time of its assets, if known.
+    pub build_id: Option<String>,
+    /// The path the binary was, or would have been, written to.
+    pub installed_path: PathBuf,
+    /// The target of the asset the binary was taken from. This is empty if
+    /// the installation was skipped before anything was downloaded.
+    pub target_used: String,
+    /// The size of the binary in bytes. This is the size of the downloaded
+    /// binary even if it was not written, such as in a dry-run, and `0` if
+    /// the installation was skipped before anything was downloaded.
+    pub bytes_written: u64,
+    /// The number of bytes downloaded for the binary, summed over the assets
+    /// tried. This is the size of each asset as reported by the forge, or
+    /// the size of the files extracted from it if unknown, and `0` if nothing
+    /// was downloaded.
+    pub bytes_downloaded: u64,
+    /// How long downloading and extracting the assets took, or `None` if
+    /// nothing was downloaded.
+    pub download_duration: Option<Duration>,
+    /// Whether this was a dry-run, in which case nothing was written.
+    pub dry_run: bool,
+    /// What was done with the binary.
+    pub status: SwapStatus,
+    /// The paths of the files installed from the
+    /// [`companion_assets`](crate::BinswapGithubBuilder::companion_assets).
+    pub companion_paths: Vec<PathBuf>,
+    /// In a dry-run, the version of the binary at the target path, as
+    /// reported by its `--version`. This is `None` if it could not be
+    /// determined, or it was not a dry-run.
+    pub previous_version: Option<String>,
+}
+
+/// What was done with the downloaded binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SwapStatus {
+    /// The binary was written to the target path.
+    Swapped,
+    /// The installation was not confirmed, by the user or by
+    /// [`on_before_swap`](crate::BinswapGithubBuilder::on_before_swap).
+    Declined,
+    /// The installation was skipped.
+    Skipped {
+        /// Why the installation was skipped.
+        reason: SkipReason,
+    },
+}
+
+/// Why an installation was skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SkipReason {
+    /// The downloaded binary is identical to the one already installed.
+    Identical,
+    /// The installed binary is already up to date.
+    UpToDate,
+    /// The release was rejected by
+    /// [`install_if`](crate::BinswapGithubBuilder::install_if).
+    Rejected,
+    /// The release was published more recently than
+    /// [`min_release_age`](crate::BinswapGithubBuilder::min_release_age)
+    /// allows.
+    TooRecent {
+        /// When the release was published, in ISO 8601 format.
+        published_at: Option<String>,
+    },
+    /// The release was installed
+    /// [`provisionally`](crate::BinswapGithubBuilder::provisional) before,
+    /// but rolled back since it was not confirmed healthy.
+    Unhealthy {
+        /// The version which was rolled back.
+        version: String,
+    },
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Identical => write!(f, "the binary is identical to the downloaded one"),
+            SkipReason::UpToDate => write!(f, "the binary is already up to date"),
+            SkipReason::Rejected => write!(f, "the release was rejected"),
+            SkipReason::TooRecent {
+                published_at: Some(published_at),
+            } => write!(
+                f,
+                "the release was published too recently, at {published_at}"
+            ),
+            SkipReason::TooRecent { published_at: None } => {
+                write!(f, "the release has no publication date")
+            }
+            SkipReason::Unhealthy { version } => write!(
+                f,
+                "version {version} was rolled back since it was not confirmed healthy"
+            ),
+        }
+    }
+}
+
+/// The result of [`fetch_and_install_all_into`](crate::BinswapGithub::fetch_and_install_all_into).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct InstallAllOutcome {
+    /// The version of the release the binaries were taken from.
+    pub version: String,
+    /// Identifies the build of the release, see [`SwapOutcome::build_id`].
+    pub build_id: Option<String>,
+    /// The target of the asset the binaries were taken from. This is empty if
+    /// the installation was skipped before anything was downloaded.
+    pub target: String,
+    /// The paths of the matched binaries, relative to the root of the asset.
+    pub matched: Vec<PathBuf>,
+    /// The paths the matched binaries were written to. This is empty if the
+    /// installation was not confirmed.
+    pub installed: Vec<PathBuf>,
+    /// The number of bytes downloaded for the binaries, see
+    /// [`SwapOutcome::bytes_downloaded`].
+    pub bytes_downloaded: u64,
+    /// How long downloading and extracting the assets took, or `None` if
+    /// nothing was downloaded.
+    pub download_duration: Option<Duration>,
+    /// Whether this was a dry-run, in which case nothing was written.
+    pub dry_run: bool,
+    /// What was done with the binaries.
+    pub status: SwapStatus,
+    /// The paths of the files installed from the
+    /// [`companion_assets`](crate::BinswapGithubBuilder::companion_assets).
+    pub companion_paths: Vec<PathBuf>,
+}
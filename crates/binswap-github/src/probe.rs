@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use binstalk::{
+    fetchers::{Data, Fetcher, GhCrateMeta, TargetData},
+    helpers::{gh_api_client::GhApiClient, remote::Client},
+    manifests::cargo_toml_binstall::{PkgFmt, PkgMeta},
+};
+use color_eyre::{eyre::eyre, Result};
+
+use crate::{
+    report::{ExecutableCommand, Print, Stylize},
+    AssetInfo, BinswapGithub, ForgeKind, ReleaseInfo, Resolved, SwapEvent,
+};
+
+/// The asset which would be downloaded for a target, as determined by
+/// [`resolve_asset`](BinswapGithub::resolve_asset).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ResolvedAsset {
+    /// The version of the release the asset belongs to.
+    pub version: String,
+    /// The target the asset was found for.
+    pub target: String,
+    /// The URL the asset would be downloaded from.
+    pub url: String,
+    /// The format of the asset.
+    pub pkg_fmt: PkgFmt,
+}
+
+/// What is needed to look for the asset of the resolved release for each of
+/// the candidate targets.
+pub(crate) struct Prober {
+    client: Client,
+    gh_api_client: GhApiClient,
+    data: Arc<Data>,
+    meta: PkgMeta,
+    pub(crate) version: String,
+    pub(crate) release: Option<ReleaseInfo>,
+}
+
+/// An asset found for a target.
+pub(crate) struct Probe<'a> {
+    /// The fetcher which found the asset, and downloads it.
+    pub(crate) resolver: Arc<dyn Fetcher>,
+    /// The asset among those of the release, if it could be identified.
+    pub(crate) asset: Option<(&'a AssetInfo, PkgFmt)>,
+}
+
+impl BinswapGithub {
+    /// Determines which asset would be downloaded for `target`, without
+    /// downloading it, following the same steps as an installation. Returns
+    /// `None` if no asset was found for the target.
+    pub async fn resolve_asset(&self, target: &str) -> Result<Option<ResolvedAsset>> {
+        let this = &self.fresh_budget();
+        let client = this.client()?;
+        let mut resolved = this.resolve(&client).await?;
+        if resolved.release.is_none() {
+            let release = this
+                .fetch_release_for_version(&client, &resolved.version)
+                .await?;
+            resolved.release = Some(release);
+        }
+
+        let prober = this.prober(&client, resolved).await?;
+        let Some(probe) = this.probe(&prober, target).await? else {
+            return Ok(None);
+        };
+        let Some((asset, _)) = probe.asset else {
+            return Err(eyre!("could not determine the download URL for `{target}`"));
+        };
+
+        Ok(Some(ResolvedAsset {
+            version: prober.version.clone(),
+            target: target.to_string(),
+            url: asset.browser_download_url.clone(),
+            pkg_fmt: probe.resolver.pkg_fmt(),
+        }))
+    }
+
+    /// Prepares to look for the asset of the `resolved` release.
+    pub(crate) async fn prober(&self, client: &Client, resolved: Resolved) -> Result<Prober> {
+        let meta = self.fetch_binstall_meta(client, &resolved).await?;
+        if let Some(release) = &resolved.release {
+            release.ensure_has_assets()?;
+        }
+        let gh_api_client = GhApiClient::new(client.clone(), self.token().map(Into::into));
+        let data = Arc::new(Data::new(
+            self.asset_name
+                .as_deref()
+                .map(Into::into)
+                .unwrap_or_else(|| self.bin_name.as_str().into()),
+            resolved.version.as_str().into(),
+            Some(format!(
+                "{}/{}/{}/",
+                self.download_base_url().trim_end_matches('/'),
+                self.repo_author,
+                self.repo_name
+            )),
+        ));
+
+        Ok(Prober {
+            client: client.clone(),
+            gh_api_client,
+            data,
+            meta,
+            version: resolved.version,
+            release: resolved.release,
+        })
+    }
+
+    /// Looks for the asset of the release for `target`.
+    pub(crate) async fn probe<'a>(
+        &self,
+        prober: &'a Prober,
+        target: &str,
+    ) -> Result<Option<Probe<'a>>> {
+        let release = prober.release.as_ref();
+        let version = &prober.version;
+
+        let mut target_meta = self.target_meta(&prober.meta, target);
+        let chosen = match release {
+            Some(release) if self.has_asset_filters() => {
+                match self.filtered_asset(release, target)? {
+                    Some(chosen) => Some(chosen),
+                    None => return Ok(None),
+                }
+            }
+            // Assets on other forges are not necessarily where the default
+            // templates point, so they are matched by name instead.
+            Some(release) => self.mapped_asset(release, target).or_else(|| {
+                (self.forge != ForgeKind::GitHub)
+                    .then(|| self.matched_asset(release, version, target, &target_meta))
+                    .flatten()
+            }),
+            None => None,
+        };
+        if let Some((asset, pkg_fmt)) = chosen {
+            target_meta.pkg_url = Some(asset.browser_download_url.clone());
+            target_meta.pkg_fmt = Some(self.pkg_fmt.unwrap_or(pkg_fmt));
+        }
+
+        self.emit(SwapEvent::ProbingTarget(target.to_string()));
+        self.stderr()
+            .execute(Print("Looking for binary for target ".magenta().italic()))?
+            .execute(Print(target))?
+            .execute(Print("...\n".magenta().italic()))?;
+
+        // The custom `pkg_url` is tried first, falling back to the one in
+        // the metadata, or the default templates.
+        let mut candidate_metas = vec![];
+        if let (Some(pkg_url), None) = (&self.pkg_url, chosen) {
+            let mut custom_meta = target_meta.clone();
+            custom_meta.pkg_url = Some(pkg_url.clone());
+            candidate_metas.push(custom_meta);
+        }
+        candidate_metas.push(target_meta);
+        for candidate_meta in candidate_metas {
+            let resolver = GhCrateMeta::new(
+                prober.client.clone(),
+                prober.gh_api_client.clone(),
+                prober.data.clone(),
+                Arc::new(TargetData {
+                    target: target.to_string(),
+                    meta: candidate_meta.clone(),
+                }),
+            );
+            if Arc::clone(&resolver).find().await?? {
+                let asset = release.and_then(|release| {
+                    self.matched_asset(release, version, target, &candidate_meta)
+                });
+                return Ok(Some(Probe { resolver, asset }));
+            }
+        }
+
+        Ok(None)
+    }
+}
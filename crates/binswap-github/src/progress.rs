@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use binstalk::helpers::remote::{Client, Url};
+use color_eyre::Result;
+use futures_util::StreamExt;
+use tokio::io::AsyncWriteExt;
+
+use crate::{BinswapGithub, SwapEvent};
+
+/// The progress of an update, reported to
+/// [`on_progress`](crate::BinswapGithubBuilder::on_progress).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProgressEvent {
+    /// The latest version is being looked up.
+    ResolvingVersion,
+    /// The version to install has been determined.
+    VersionResolved(String),
+    /// Looking for a release asset for the target.
+    ProbingTarget(String),
+    /// A release asset is being downloaded. This is reported with
+    /// `downloaded` being `0` when the download starts, and then as each
+    /// chunk of the asset is received.
+    Downloading {
+        /// How many bytes of the asset have been downloaded.
+        downloaded: u64,
+        /// The size of the asset in bytes, if known.
+        total: Option<u64>,
+    },
+    /// The downloaded asset is being extracted. This is reported after
+    /// downloading assets and distribution packages, and before extracting
+    /// archives which are installed from disk.
+    Extracting,
+    /// The binary has been written to its destination.
+    Swapped,
+    /// A line of the status messages, which are reported here rather than
+    /// printed to stderr when the `terminal-output` feature is disabled.
+    Status(String),
+}
+
+impl BinswapGithub {
+    /// Reports `event` to the `on_progress` hook, if set.
+    pub(crate) fn progress(&self, event: ProgressEvent) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(event);
+        }
+    }
+
+    /// Downloads `url` to `path`, reporting the progress as each chunk of it
+    /// is received. `total` is the expected size, if known. Returns the
+    /// number of bytes downloaded.
+    pub(crate) async fn download_with_progress(
+        &self,
+        client: &Client,
+        url: &Url,
+        path: &Path,
+        total: Option<u64>,
+    ) -> Result<u64> {
+        self.download_to(client, url, path, |downloaded| {
+            self.progress(ProgressEvent::Downloading { downloaded, total })
+        })
+        .await
+    }
+
+    /// Downloads `url` to `path`, calling `on_chunk` with the number of
+    /// bytes downloaded so far as each chunk of it is received. Returns the
+    /// number of bytes downloaded.
+    pub(crate) async fn download_to(
+        &self,
+        client: &Client,
+        url: &Url,
+        path: &Path,
+        mut on_chunk: impl FnMut(u64),
+    ) -> Result<u64> {
+        let mut chunks = self.get_download(client, url).await?.bytes_stream();
+        let mut file = tokio::fs::File::create(path).await?;
+        let mut downloaded = 0;
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            file.write_all(&chunk).await?;
+            downloaded += chunk.len() as u64;
+            on_chunk(downloaded);
+        }
+        file.flush().await?;
+        Ok(downloaded)
+    }
+
+    /// Reports the progress corresponding to `event`, if any.
+    pub(crate) fn progress_of(&self, event: &SwapEvent) {
+        let event = match event {
+            SwapEvent::ResolvingVersion => ProgressEvent::ResolvingVersion,
+            SwapEvent::VersionResolved(version) => ProgressEvent::VersionResolved(version.clone()),
+            SwapEvent::ProbingTarget(target) => ProgressEvent::ProbingTarget(target.clone()),
+            SwapEvent::Swapped(_) => ProgressEvent::Swapped,
+            _ => return,
+        };
+        self.progress(event);
+    }
+}
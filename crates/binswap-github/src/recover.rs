@@ -0,0 +1,89 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{eyre::Context, Result};
+
+use crate::{
+    health::{backup_path, remove_if_exists, sibling},
+    report::{ExecutableCommand, Print, ResetColor, Stylize},
+    BinswapGithub,
+};
+
+impl BinswapGithub {
+    /// Restores the previous binary at `target_binary` if an earlier update
+    /// was interrupted after moving it out of the way, but before moving the
+    /// new binary into place. Returns whether it was restored.
+    ///
+    /// This is also done before every update of `target_binary`.
+    pub async fn recover(&self, target_binary: impl AsRef<Path>) -> Result<bool> {
+        let target_binary = target_binary.as_ref();
+
+        let _lock = self.lock(target_binary).await?;
+        let swap_backup = swap_backup_path(target_binary);
+        if tokio::fs::try_exists(target_binary).await? {
+            remove_old_binary(&swap_backup).await?;
+            return Ok(false);
+        }
+
+        for backup in [swap_backup, backup_path(target_binary)] {
+            match tokio::fs::rename(&backup, target_binary).await {
+                Ok(()) => {
+                    self.stderr()
+                        .execute(Print("Restored ".yellow()))?
+                        .execute(Print(format!("`{}`", target_binary.display())))?
+                        .execute(Print(" after an interrupted update\n".yellow()))?
+                        .execute(ResetColor)?;
+                    return Ok(true);
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e).wrap_err("failed to restore the previous binary"),
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+/// Removes the previous binary at `backup_bin` after a swap.
+///
+/// On Windows a running executable can be renamed but not removed, so when
+/// replacing the current executable, its backup is instead scheduled for
+/// removal on the next reboot. If that is not allowed either, it is left
+/// behind and removed by [`BinswapGithub::recover`] on the next update.
+pub(crate) async fn remove_old_binary(backup_bin: &Path) -> Result<()> {
+    #[cfg(windows)]
+    match tokio::fs::remove_file(backup_bin).await {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            remove_on_reboot(backup_bin);
+            return Ok(());
+        }
+        Ok(()) => return Ok(()),
+        Err(_) => {}
+    }
+    remove_if_exists(backup_bin).await
+}
+
+/// Asks Windows to remove `path` on the next reboot, which requires
+/// administrator privileges.
+#[cfg(windows)]
+fn remove_on_reboot(path: &Path) {
+    use std::os::windows::ffi::OsStrExt;
+
+    use windows_sys::Win32::Storage::FileSystem::{MoveFileExW, MOVEFILE_DELAY_UNTIL_REBOOT};
+
+    let path: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    // SAFETY: `path` is a null-terminated wide string which outlives the
+    // call, and a null destination means the file is removed.
+    unsafe {
+        MoveFileExW(path.as_ptr(), std::ptr::null(), MOVEFILE_DELAY_UNTIL_REBOOT);
+    }
+}
+
+/// Where the previous binary is kept while it is swapped with the new one.
+/// It is next to the target rather than in the temporary directory, so that
+/// it is found again after a crash.
+pub(crate) fn swap_backup_path(target_binary: &Path) -> PathBuf {
+    sibling(target_binary, "binswap-old")
+}
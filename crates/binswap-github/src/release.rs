@@ -0,0 +1,565 @@
+use std::{cmp::Ordering, env, num::NonZeroU64, time::Duration};
+
+use binstalk::helpers::remote::{Client, Response, StatusCode, Url};
+use color_eyre::{eyre::eyre, Result};
+use futures_util::{future, pin_mut, stream, Stream, StreamExt, TryStreamExt};
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+
+use crate::{netrc, BinswapGithub, Error, ForgeKind};
+
+/// The maximum number of requests made to the GitHub API at once.
+pub(crate) const MAX_CONCURRENT_REQUESTS: usize = 4;
+/// The number of releases fetched per request when listing releases, which is
+/// the maximum allowed by GitHub and GitLab.
+const RELEASES_PER_PAGE: u32 = 100;
+/// The number of releases fetched per request on Gitea, which is the default
+/// maximum of its API.
+const GITEA_RELEASES_PER_PAGE: u32 = 50;
+/// The number of versions listed when no release matches a version range.
+const CLOSEST_VERSIONS: usize = 3;
+
+/// How to choose between releases whose versions have the same precedence,
+/// see [`tiebreak_by`](crate::BinswapGithubBuilder::tiebreak_by). Releases
+/// which are still tied are ordered by their tag names, and then by their
+/// build metadata, such that the choice is always the same.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Tiebreak {
+    /// The most recently published release. Releases without a known
+    /// publication date lose against those with one.
+    #[default]
+    PublishedDate,
+    /// The release with the greatest tag name.
+    TagName,
+    /// The release with the greatest build metadata, with numeric parts
+    /// compared as numbers.
+    BuildMetadata,
+}
+
+impl Tiebreak {
+    /// Compares the releases `a` and `b` of the versions `a_version` and
+    /// `b_version`, by precedence first and then by the tiebreak.
+    pub(crate) fn cmp(
+        self,
+        (a_version, a): (&Version, &ReleaseInfo),
+        (b_version, b): (&Version, &ReleaseInfo),
+    ) -> Ordering {
+        let published_at = |release: &ReleaseInfo| {
+            release
+                .published_at
+                .as_deref()
+                .and_then(|published_at| humantime::parse_rfc3339(published_at).ok())
+        };
+        let by_tag_name = || a.tag_name.cmp(&b.tag_name);
+        let by_build = || a_version.build.cmp(&b_version.build);
+
+        a_version
+            .cmp_precedence(b_version)
+            .then_with(|| match self {
+                Tiebreak::PublishedDate => published_at(a)
+                    .cmp(&published_at(b))
+                    .then_with(by_tag_name)
+                    .then_with(by_build),
+                Tiebreak::TagName => by_tag_name().then_with(by_build),
+                Tiebreak::BuildMetadata => by_build().then_with(by_tag_name),
+            })
+    }
+}
+
+/// Metadata of a release on GitHub, or another forge.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ReleaseInfo {
+    /// The name of the tag of the release.
+    pub tag_name: String,
+    /// The title of the release.
+    pub name: Option<String>,
+    /// The description of the release.
+    pub body: Option<String>,
+    /// Whether the release is a draft.
+    pub draft: bool,
+    /// Whether the release is marked as a pre-release.
+    pub prerelease: bool,
+    /// When the release was created, in ISO 8601 format.
+    pub created_at: Option<String>,
+    /// When the release was published, in ISO 8601 format.
+    pub published_at: Option<String>,
+    /// The URL of the release page.
+    pub html_url: String,
+    /// The files attached to the release.
+    pub assets: Vec<AssetInfo>,
+}
+
+impl ReleaseInfo {
+    /// The version of the release, which is the tag name without the leading
+    /// `v`.
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    /// Fails with [`Error::ReleaseHasNoAssets`] if no files are attached to
+    /// the release.
+    pub(crate) fn ensure_has_assets(&self) -> Result<()> {
+        if self.assets.is_empty() {
+            return Err(Error::ReleaseHasNoAssets {
+                tag: self.tag_name.clone(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Metadata of a file attached to a release on GitHub, or another forge.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct AssetInfo {
+    /// The file name of the asset.
+    pub name: String,
+    /// The URL the asset can be downloaded from.
+    pub browser_download_url: String,
+    /// The media type of the asset, such as `application/gzip`. This is
+    /// empty if the forge does not report it.
+    #[serde(default)]
+    pub content_type: String,
+    /// The size of the asset in bytes, or 0 if the forge does not report it.
+    pub size: u64,
+    /// When the asset was last updated, in ISO 8601 format.
+    pub updated_at: Option<String>,
+}
+
+/// The body of an unsuccessful response from the GitHub API.
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl BinswapGithub {
+    /// Fetches the metadata of the releases with the given tags. The releases
+    /// are fetched concurrently, and a release which could not be fetched
+    /// results in an error in its place rather than failing the whole batch.
+    pub async fn fetch_releases_by_tags(
+        &self,
+        tags: &[String],
+    ) -> Result<Vec<Result<ReleaseInfo>>> {
+        let this = &self.fresh_budget();
+        let client = this.client()?;
+
+        Ok(stream::iter(tags)
+            .map(|tag| this.fetch_release_by_tag(&client, tag))
+            .buffered(MAX_CONCURRENT_REQUESTS)
+            .collect()
+            .await)
+    }
+
+    /// Streams the releases of the repository, newest first. Pages of
+    /// releases are fetched as the stream is consumed, so a consumer which
+    /// stops early does not fetch the rest. The stream ends after the first
+    /// error.
+    pub fn releases_paginated(&self) -> impl Stream<Item = Result<ReleaseInfo>> + '_ {
+        self.fresh_budget().into_releases()
+    }
+
+    /// Lists the versions of all releases of the repository, newest first,
+    /// without downloading anything. The versions are the tags of the
+    /// releases without the leading `v`, as used when installing them.
+    pub async fn list_versions(&self) -> Result<Vec<String>> {
+        self.fresh_budget()
+            .into_releases()
+            .map_ok(|release| release.version().to_string())
+            .try_collect()
+            .await
+    }
+
+    /// Streams the releases of the repository like
+    /// [`releases_paginated`](Self::releases_paginated), as part of an
+    /// operation which spends the same retry budget.
+    pub(crate) fn all_releases(&self) -> impl Stream<Item = Result<ReleaseInfo>> {
+        self.clone().into_releases()
+    }
+
+    fn into_releases(self) -> impl Stream<Item = Result<ReleaseInfo>> {
+        let first = self.api_url("releases").map(|mut url| {
+            match self.forge {
+                ForgeKind::Gitea => url
+                    .query_pairs_mut()
+                    .append_pair("limit", &GITEA_RELEASES_PER_PAGE.to_string()),
+                _ => url
+                    .query_pairs_mut()
+                    .append_pair("per_page", &RELEASES_PER_PAGE.to_string()),
+            };
+            url
+        });
+        let client = self.client();
+        let state = Some((self, client, first));
+
+        stream::unfold(state, move |state| async move {
+            let (this, client, url) = state?;
+            let res = async {
+                let (client, url) = (client?, url?);
+                let (releases, next) = this.fetch_releases_page(&client, &url).await?;
+                Ok((releases, client, next))
+            }
+            .await;
+            Some(match res {
+                Ok((releases, client, next)) => {
+                    let state = next.map(|next| (this, Ok(client), Ok(next)));
+                    (Ok(releases), state)
+                }
+                Err(e) => (Err(e), None),
+            })
+        })
+        .flat_map(|page: Result<Vec<ReleaseInfo>>| {
+            stream::iter(match page {
+                Ok(releases) => releases.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            })
+        })
+    }
+
+    /// Fetches a page of releases, and the URL of the next page if there is
+    /// one.
+    async fn fetch_releases_page(
+        &self,
+        client: &Client,
+        url: &Url,
+    ) -> Result<(Vec<ReleaseInfo>, Option<Url>)> {
+        self.retrying(|| async {
+            let res = self.api_send(client, url).await?.error_for_status()?;
+            let next = res
+                .headers()
+                .get("link")
+                .and_then(|link| link.to_str().ok())
+                .and_then(next_page_url);
+            let body = res.bytes().await?;
+            Ok((self.parse_releases(&body)?, next))
+        })
+        .await
+    }
+
+    pub(crate) fn client(&self) -> Result<Client> {
+        if let Some(client) = &self.client {
+            return Ok(client.clone());
+        }
+        Ok(Client::new(
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+            None,
+            Duration::from_millis(5),
+            NonZeroU64::new(1).unwrap(),
+            None,
+        )?)
+    }
+
+    /// The URL of `path` within the repository in the API of the forge.
+    pub(crate) fn api_url(&self, path: &str) -> Result<Url> {
+        let base = self.api_base_url().trim_end_matches('/');
+        Ok(Url::parse(&match self.forge {
+            ForgeKind::GitHub | ForgeKind::Gitea => format!(
+                "{base}/repos/{}/{}/{path}",
+                self.repo_author, self.repo_name
+            ),
+            ForgeKind::GitLab => format!(
+                "{base}/projects/{}/{path}",
+                path_segment(&format!("{}/{}", self.repo_author, self.repo_name))
+            ),
+        })?)
+    }
+
+    /// The token to authenticate with the forge, if any.
+    pub(crate) fn token(&self) -> Option<String> {
+        self.github_token
+            .as_ref()
+            .map(|token| token.0.clone())
+            .or_else(|| env_token(self.forge))
+            .or_else(|| self.use_netrc.then(|| self.netrc_token()).flatten())
+    }
+
+    /// The token for the API or download host from the `.netrc` file.
+    fn netrc_token(&self) -> Option<String> {
+        let hosts: Vec<String> = [self.api_base_url(), self.download_base_url()]
+            .into_iter()
+            .filter_map(|base| Url::parse(base).ok()?.host_str().map(String::from))
+            .collect();
+        netrc::netrc_token(&hosts)
+    }
+
+    /// Sends a `GET` request to the API of the forge, authenticated if a
+    /// token is available. Being forbidden from accessing the resource
+    /// results in an error, other unsuccessful statuses are left for the
+    /// caller to handle.
+    pub(crate) async fn api_send(&self, client: &Client, url: &Url) -> Result<Response> {
+        let accept = match self.forge {
+            ForgeKind::GitHub => "application/vnd.github+json",
+            ForgeKind::GitLab | ForgeKind::Gitea => "application/json",
+        };
+        self.api_send_accepting(client, url, accept).await
+    }
+
+    /// Like [`api_send`](Self::api_send), but requesting the media type
+    /// `accept`.
+    pub(crate) async fn api_send_accepting(
+        &self,
+        client: &Client,
+        url: &Url,
+        accept: &str,
+    ) -> Result<Response> {
+        let req = client.get(url.clone()).header("Accept", accept);
+        let req = match self.token() {
+            Some(token) => req.bearer_auth(&token),
+            None => req,
+        };
+        let res = req.send(false).await?;
+
+        if res.status() != StatusCode::FORBIDDEN {
+            return Ok(res);
+        }
+
+        let header = |name: &str| {
+            res.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        if header("x-ratelimit-remaining").as_deref() == Some("0") {
+            return Err(Error::RateLimited {
+                reset: header("x-ratelimit-reset").and_then(|reset| reset.parse().ok()),
+            }
+            .into());
+        }
+        let accepted_permissions = header("x-accepted-github-permissions");
+        let body = res.bytes().await?;
+        let message = serde_json::from_slice::<ErrorResponse>(&body)
+            .map(|res| res.message)
+            .unwrap_or_default();
+        if message.contains("rate limit") {
+            return Err(Error::RateLimited { reset: None }.into());
+        }
+
+        if self.token().is_none() {
+            return Err(eyre!("access to `{url}` was forbidden: {message}"));
+        }
+
+        Err(Error::InsufficientTokenScope {
+            permission: accepted_permissions.unwrap_or_else(|| "contents=read".to_string()),
+            message,
+        }
+        .into())
+    }
+
+    /// Fetches the latest release, from the GraphQL API if `use_graphql` is
+    /// set and a token is available, falling back to the REST API.
+    pub(crate) async fn fetch_latest_release_uncached(
+        &self,
+        client: &Client,
+    ) -> Result<ReleaseInfo> {
+        if self.allow_prerelease || self.allow_draft {
+            return self.fetch_newest_release().await;
+        }
+        if self.use_graphql && self.forge == ForgeKind::GitHub && self.token().is_some() {
+            if let Ok(release) = self.graphql_latest_release(client).await {
+                return Ok(release);
+            }
+        }
+        self.fetch_latest_release_rest(client).await
+    }
+
+    pub(crate) async fn fetch_latest_release_rest(&self, client: &Client) -> Result<ReleaseInfo> {
+        let url = match self.forge {
+            ForgeKind::GitHub | ForgeKind::Gitea => self.api_url("releases/latest")?,
+            ForgeKind::GitLab => self.api_url("releases/permalink/latest")?,
+        };
+        self.retrying(|| async {
+            let body = self.api_send(client, &url).await?.bytes().await?;
+            self.parse_release(&body)
+        })
+        .await
+    }
+
+    /// Fetches the newest release, which may be a pre-release if
+    /// `allow_prerelease` is set, or a draft if `allow_draft` is set.
+    async fn fetch_newest_release(&self) -> Result<ReleaseInfo> {
+        if self.allow_draft && self.token().is_none() {
+            return Err(eyre!(
+                "a GitHub token is required to look up draft releases"
+            ));
+        }
+
+        let releases = self.all_releases().try_filter(|release| {
+            future::ready(
+                (!release.prerelease || self.allow_prerelease)
+                    && (!release.draft || self.allow_draft),
+            )
+        });
+        pin_mut!(releases);
+        releases
+            .try_next()
+            .await?
+            .ok_or_else(|| eyre!("the repository has no release which may be installed"))
+    }
+
+    /// Fetches the release of `version`, whose tag might have a leading `v`.
+    pub(crate) async fn fetch_release_for_version(
+        &self,
+        client: &Client,
+        version: &str,
+    ) -> Result<ReleaseInfo> {
+        match self.fetch_release_by_tag(client, version).await {
+            Err(e) if matches!(e.downcast_ref(), Some(Error::ReleaseNotFound { .. })) => {
+                self.fetch_release_by_tag(client, &format!("v{version}"))
+                    .await
+            }
+            res => res,
+        }
+    }
+
+    /// Fetches the release with the highest version matching `req`, going
+    /// through all releases of the repository, choosing between those of the
+    /// same precedence by `tiebreak_by`. Releases whose tag is not a
+    /// semantic version are ignored, as are drafts unless `allow_draft` is
+    /// set.
+    pub(crate) async fn fetch_release_matching(&self, req: &VersionReq) -> Result<ReleaseInfo> {
+        let releases: Vec<(Version, ReleaseInfo)> = self
+            .all_releases()
+            .try_filter_map(|release| async move {
+                Ok(match Version::parse(release.version()) {
+                    Ok(version) if !release.draft || self.allow_draft => Some((version, release)),
+                    _ => None,
+                })
+            })
+            .try_collect()
+            .await?;
+
+        if let Some((_, release)) = releases
+            .iter()
+            .filter(|(version, _)| req.matches(version))
+            .max_by(|(a, a_release), (b, b_release)| {
+                self.tiebreak_by.cmp((a, a_release), (b, b_release))
+            })
+        {
+            return Ok(release.clone());
+        }
+
+        let mut versions: Vec<Version> = releases.into_iter().map(|(version, _)| version).collect();
+        if let Some(pivot) = req.comparators.first() {
+            let distance = |version: &Version| {
+                (
+                    version.major.abs_diff(pivot.major),
+                    version.minor.abs_diff(pivot.minor.unwrap_or(0)),
+                    version.patch.abs_diff(pivot.patch.unwrap_or(0)),
+                )
+            };
+            versions.sort_by_key(distance);
+        }
+        versions.truncate(CLOSEST_VERSIONS);
+        versions.sort_by(|a, b| b.cmp(a));
+
+        Err(Error::NoMatchingVersion {
+            req: req.to_string(),
+            closest: versions.iter().map(Version::to_string).collect(),
+        }
+        .into())
+    }
+
+    pub(crate) async fn fetch_release_by_tag(
+        &self,
+        client: &Client,
+        tag: &str,
+    ) -> Result<ReleaseInfo> {
+        let url = match self.forge {
+            ForgeKind::GitHub | ForgeKind::Gitea => {
+                self.api_url(&format!("releases/tags/{tag}"))?
+            }
+            ForgeKind::GitLab => self.api_url(&format!("releases/{}", path_segment(tag)))?,
+        };
+        self.retrying(|| async {
+            let res = self.api_send(client, &url).await?;
+            if res.status() == StatusCode::NOT_FOUND {
+                return Err(Error::ReleaseNotFound {
+                    tag: tag.to_string(),
+                }
+                .into());
+            }
+            self.parse_release(&res.bytes().await?)
+        })
+        .await
+    }
+}
+
+/// The URL of the next page from a `Link` header, like
+/// `<https://...?page=2>; rel="next", <https://...?page=5>; rel="last"`.
+fn next_page_url(link: &str) -> Option<Url> {
+    link.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#)
+            .then(|| Url::parse(url.trim().trim_start_matches('<').trim_end_matches('>')).ok())?
+    })
+}
+
+/// The version range given by `version`, if it is a range like `^1.2` or
+/// `>=1.0, <2.0` rather than a concrete version or tag, which are looked up
+/// exactly.
+pub(crate) fn version_range(version: &str) -> Option<VersionReq> {
+    let is_range = version.contains(['^', '~', '<', '>', '=', '*', ',']);
+    is_range.then(|| VersionReq::parse(version).ok())?
+}
+
+/// Escapes `/` in `s`, such that it is a single segment of a URL path, as
+/// GitLab expects of project paths and tags.
+fn path_segment(s: &str) -> String {
+    s.replace('/', "%2F")
+}
+
+/// The token for `forge` configured in the environment, if any.
+fn env_token(forge: ForgeKind) -> Option<String> {
+    match forge {
+        ForgeKind::GitHub => env::var("GH_TOKEN").or_else(|_| env::var("GITHUB_TOKEN")),
+        ForgeKind::GitLab => env::var("GITLAB_TOKEN"),
+        ForgeKind::Gitea => env::var("GITEA_TOKEN"),
+    }
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag_name: &str, published_at: &str) -> (Version, ReleaseInfo) {
+        let release = ReleaseInfo {
+            tag_name: tag_name.to_string(),
+            name: None,
+            body: None,
+            draft: false,
+            prerelease: false,
+            created_at: None,
+            published_at: Some(published_at.to_string()),
+            html_url: String::new(),
+            assets: vec![],
+        };
+        (Version::parse(release.version()).unwrap(), release)
+    }
+
+    #[test]
+    fn tiebreak_orders_releases_of_the_same_precedence() {
+        let releases = [
+            release("v1.2.3+build.10", "2024-01-01T00:00:00Z"),
+            release("v1.2.3+build.9", "2024-02-01T00:00:00Z"),
+            release("v1.2.2+build.11", "2024-03-01T00:00:00Z"),
+        ];
+        let highest = |tiebreak: Tiebreak| {
+            let (_, release) = releases
+                .iter()
+                .max_by(|(a, a_release), (b, b_release)| {
+                    tiebreak.cmp((a, a_release), (b, b_release))
+                })
+                .unwrap();
+            release.tag_name.as_str()
+        };
+
+        assert_eq!(highest(Tiebreak::PublishedDate), "v1.2.3+build.9");
+        assert_eq!(highest(Tiebreak::TagName), "v1.2.3+build.9");
+        assert_eq!(highest(Tiebreak::BuildMetadata), "v1.2.3+build.10");
+    }
+}
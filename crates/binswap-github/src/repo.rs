@@ -0,0 +1,90 @@
+use std::{cmp::Ordering, fmt};
+
+use color_eyre::{eyre::eyre, Result};
+use futures_util::{stream, StreamExt};
+use semver::Version;
+
+use crate::{release::MAX_CONCURRENT_REQUESTS, BinswapGithub, ReleaseInfo};
+
+/// A repository on GitHub.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RepoId {
+    /// The name of the author or team of the repository.
+    pub author: String,
+    /// The name of the repository.
+    pub name: String,
+}
+
+impl fmt::Display for RepoId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.author, self.name)
+    }
+}
+
+impl BinswapGithub {
+    /// Looks up the latest release of each of the repositories, given as
+    /// pairs of author and name, and returns the repository with the highest
+    /// version along with the version. This is meant for tools published
+    /// under several repositories, such as mirrors, to find out which
+    /// repository to install from. Repositories whose versions have the same
+    /// precedence are chosen between by `tiebreak_by`, and otherwise the
+    /// first of them is returned.
+    ///
+    /// Repositories whose latest release could not be fetched, or whose
+    /// version is not a semantic version, are ignored. If none are left, the
+    /// first error is returned.
+    pub async fn latest_across(&self, repos: Vec<(String, String)>) -> Result<(RepoId, String)> {
+        let this = &self.fresh_budget();
+        let client = this.client()?;
+
+        let results: Vec<Result<(RepoId, ReleaseInfo)>> = stream::iter(repos)
+            .map(|(author, name)| {
+                let mut repo = this.clone();
+                repo.repo_author = author.clone();
+                repo.repo_name = name.clone();
+                let client = client.clone();
+                async move {
+                    let release = repo.fetch_latest_release(&client).await?;
+                    Ok((RepoId { author, name }, release))
+                }
+            })
+            .buffered(MAX_CONCURRENT_REQUESTS)
+            .collect()
+            .await;
+
+        let mut newest: Option<(Version, RepoId, ReleaseInfo)> = None;
+        let mut first_error = None;
+        for res in results {
+            let (repo, release) = match res {
+                Ok(found) => found,
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                    continue;
+                }
+            };
+            let Ok(parsed) = Version::parse(release.version()) else {
+                first_error.get_or_insert_with(|| {
+                    eyre!(
+                        "the version `{}` of `{repo}` is not a semantic version",
+                        release.version()
+                    )
+                });
+                continue;
+            };
+            match &newest {
+                Some((highest, _, highest_release))
+                    if this
+                        .tiebreak_by
+                        .cmp((&parsed, &release), (highest, highest_release))
+                        != Ordering::Greater => {}
+                _ => newest = Some((parsed, repo, release)),
+            }
+        }
+
+        match (newest, first_error) {
+            (Some((_, repo, release)), _) => Ok((repo, release.version().to_string())),
+            (None, Some(e)) => Err(e),
+            (None, None) => Err(eyre!("no repositories were given")),
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use std::io::{self, Write};
+
+#[cfg(feature = "terminal-output")]
+pub(crate) use crossterm::{
+    cursor::{RestorePosition, SavePosition},
+    style::{Print, ResetColor, Stylize},
+    ExecutableCommand,
+};
+#[cfg(not(feature = "terminal-output"))]
+pub(crate) use plain::{
+    ExecutableCommand, Print, ResetColor, RestorePosition, SavePosition, Stylize,
+};
+
+use crate::{hook::Hook, BinswapGithub};
+
+/// Receives the status messages of updates, such as which target is being
+/// looked for and which version was installed, one line at a time and
+/// without any styling.
+///
+/// Set one with [`reporter`](crate::BinswapGithubBuilder::reporter) to show
+/// the messages somewhere other than stderr, which is also how they are
+/// shown at all when the `terminal-output` feature is disabled. Closures
+/// taking a `&str` are reporters.
+pub trait Reporter: Send + Sync {
+    /// Reports a single line of status.
+    fn report(&self, message: &str);
+}
+
+impl<F: Fn(&str) + Send + Sync> Reporter for F {
+    fn report(&self, message: &str) {
+        self(message)
+    }
+}
+
+impl BinswapGithub {
+    /// Where to print status messages. This is muted in `quiet` mode. They
+    /// are sent to the `reporter` if set, and are otherwise muted while the
+    /// operation is being streamed, or progress is reported to
+    /// `on_progress`. Without the `terminal-output` feature, messages are
+    /// reported to `on_progress` instead, and otherwise dropped.
+    pub(crate) fn stderr(&self) -> Box<dyn Write + Send> {
+        if self.quiet {
+            return Box::new(io::sink());
+        }
+        if let Some(reporter) = &self.reporter {
+            return Box::new(ReportWriter::new(reporter.clone()));
+        }
+        #[cfg(not(feature = "terminal-output"))]
+        if let (Some(on_progress), None) = (&self.on_progress, &self.event_sender) {
+            let on_progress = on_progress.clone();
+            let reporter: std::sync::Arc<dyn Reporter> =
+                std::sync::Arc::new(move |message: &str| {
+                    on_progress(crate::ProgressEvent::Status(message.to_string()))
+                });
+            return Box::new(ReportWriter::new(Hook(reporter)));
+        }
+        if cfg!(feature = "terminal-output")
+            && self.event_sender.is_none()
+            && self.on_progress.is_none()
+        {
+            terminal()
+        } else {
+            Box::new(io::sink())
+        }
+    }
+}
+
+/// Stderr, for printing status messages and prompts. These are only styled
+/// if stderr is a terminal and `NO_COLOR` is not set, and are otherwise
+/// printed with the escape sequences for styling removed.
+pub(crate) fn terminal() -> Box<dyn Write + Send> {
+    if use_styling() {
+        Box::new(io::stderr())
+    } else {
+        Box::new(Unstyled {
+            inner: io::stderr(),
+            stripper: AnsiStripper::default(),
+        })
+    }
+}
+
+/// Whether to style the output, see <https://no-color.org>.
+fn use_styling() -> bool {
+    #[cfg(feature = "terminal-output")]
+    {
+        use crossterm::tty::IsTty;
+        let no_color = matches!(std::env::var_os("NO_COLOR"), Some(v) if !v.is_empty());
+        !no_color && io::stderr().is_tty()
+    }
+    #[cfg(not(feature = "terminal-output"))]
+    {
+        false
+    }
+}
+
+/// Whether the user can be prompted, which requires stdin to be a terminal.
+/// Prompting also requires the terminal output, so without the
+/// `terminal-output` feature the user is never prompted.
+pub(crate) fn can_prompt() -> bool {
+    #[cfg(feature = "terminal-output")]
+    {
+        use crossterm::tty::IsTty;
+        io::stdin().is_tty()
+    }
+    #[cfg(not(feature = "terminal-output"))]
+    {
+        false
+    }
+}
+
+/// Collects what is written into lines, which are reported once complete
+/// with any ANSI escape sequences removed.
+struct ReportWriter {
+    reporter: Hook<dyn Reporter>,
+    stripper: AnsiStripper,
+    line: Vec<u8>,
+}
+
+impl ReportWriter {
+    fn new(reporter: Hook<dyn Reporter>) -> ReportWriter {
+        ReportWriter {
+            reporter,
+            stripper: AnsiStripper::default(),
+            line: Vec::new(),
+        }
+    }
+
+    fn report_line(&mut self) {
+        let line = String::from_utf8_lossy(&self.line).trim().to_string();
+        self.line.clear();
+        if !line.is_empty() {
+            self.reporter.report(&line);
+        }
+    }
+}
+
+impl Write for ReportWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for byte in self.stripper.strip(buf) {
+            if byte == b'\n' {
+                self.report_line();
+            } else {
+                self.line.push(byte);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for ReportWriter {
+    fn drop(&mut self) {
+        self.report_line();
+    }
+}
+
+/// Writes to `inner` with the ANSI escape sequences removed.
+struct Unstyled<W> {
+    inner: W,
+    stripper: AnsiStripper,
+}
+
+impl<W: Write> Write for Unstyled<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let stripped = self.stripper.strip(buf);
+        self.inner.write_all(&stripped)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Removes ANSI escape sequences from what is written, which may be split
+/// across several writes.
+#[derive(Debug, Default)]
+struct AnsiStripper {
+    state: EscapeState,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum EscapeState {
+    #[default]
+    Text,
+    /// After an escape character.
+    Escape,
+    /// Within a control sequence, which ends with a byte in the range `@` to
+    /// `~`.
+    ControlSequence,
+}
+
+impl AnsiStripper {
+    fn strip(&mut self, buf: &[u8]) -> Vec<u8> {
+        let mut stripped = Vec::with_capacity(buf.len());
+        for &byte in buf {
+            self.state = match (self.state, byte) {
+                (EscapeState::Text, b'\x1b') => EscapeState::Escape,
+                (EscapeState::Text, _) => {
+                    stripped.push(byte);
+                    EscapeState::Text
+                }
+                (EscapeState::Escape, b'[') => EscapeState::ControlSequence,
+                // Other escapes, such as saving the cursor position, are a
+                // single character.
+                (EscapeState::Escape, _) => EscapeState::Text,
+                (EscapeState::ControlSequence, b'@'..=b'~') => EscapeState::Text,
+                (EscapeState::ControlSequence, _) => EscapeState::ControlSequence,
+            };
+        }
+        stripped
+    }
+}
+
+/// Stand-ins for the parts of `crossterm` used to print status messages,
+/// which print them without styling.
+#[cfg(not(feature = "terminal-output"))]
+mod plain {
+    use std::{
+        fmt::Display,
+        io::{self, Write},
+    };
+
+    pub(crate) trait Command {
+        fn write_to(&self, w: &mut dyn Write) -> io::Result<()>;
+    }
+
+    pub(crate) struct Print<T>(pub(crate) T);
+
+    impl<T: Display> Command for Print<T> {
+        fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+            write!(w, "{}", self.0)
+        }
+    }
+
+    pub(crate) struct ResetColor;
+    pub(crate) struct SavePosition;
+    pub(crate) struct RestorePosition;
+
+    impl Command for ResetColor {
+        fn write_to(&self, _: &mut dyn Write) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Command for SavePosition {
+        fn write_to(&self, _: &mut dyn Write) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Command for RestorePosition {
+        fn write_to(&self, _: &mut dyn Write) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    pub(crate) trait ExecutableCommand {
+        fn execute(&mut self, command: impl Command) -> io::Result<&mut Self>;
+    }
+
+    impl<W: Write> ExecutableCommand for W {
+        fn execute(&mut self, command: impl Command) -> io::Result<&mut Self> {
+            command.write_to(self)?;
+            self.flush()?;
+            Ok(self)
+        }
+    }
+
+    pub(crate) trait Stylize: Sized {
+        fn green(self) -> Self {
+            self
+        }
+        fn yellow(self) -> Self {
+            self
+        }
+        fn red(self) -> Self {
+            self
+        }
+        fn magenta(self) -> Self {
+            self
+        }
+        fn italic(self) -> Self {
+            self
+        }
+        fn dim(self) -> Self {
+            self
+        }
+    }
+
+    impl Stylize for &str {}
+    impl Stylize for String {}
+}
@@ -0,0 +1,109 @@
+use std::{
+    future::Future,
+    io,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use binstalk::helpers::remote::{ReqwestError, StatusCode};
+use color_eyre::{Report, Result};
+
+use crate::{BinswapGithub, Error};
+
+impl BinswapGithub {
+    /// Runs `f` until it succeeds or fails with an error which should not be
+    /// retried. Each attempt fails with [`Error::RequestTimedOut`] if it takes
+    /// longer than `request_timeout`.
+    pub(crate) async fn retrying<T, F, Fut>(&self, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        let mut retried_at: Option<Instant> = None;
+        loop {
+            let res = match tokio::time::timeout(self.request_timeout, f()).await {
+                Ok(res) => res,
+                Err(_) => Err(Error::RequestTimedOut {
+                    timeout: self.request_timeout,
+                }
+                .into()),
+            };
+            if let Some(retried_at) = retried_at.take() {
+                *self.retry_spent.lock().unwrap() += retried_at.elapsed();
+            }
+            let backoff = jittered(
+                self.retry_backoff
+                    .saturating_mul(2u32.saturating_pow(attempt)),
+            );
+            match res {
+                Err(e)
+                    if attempt < u32::from(self.max_retries)
+                        && self.is_retryable(&e)
+                        && self.within_retry_budget(backoff) =>
+                {
+                    retried_at = Some(Instant::now());
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                    self.record(|metrics| metrics.retries += 1);
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// A copy of the configuration with none of the `total_retry_budget`
+    /// spent, which each public operation starts with, such that the budget
+    /// bounds every operation on its own.
+    pub(crate) fn fresh_budget(&self) -> BinswapGithub {
+        let mut this = self.clone();
+        this.retry_spent = Arc::default();
+        this
+    }
+
+    /// Whether waiting `backoff` before retrying stays within the
+    /// `total_retry_budget`, if any.
+    fn within_retry_budget(&self, backoff: Duration) -> bool {
+        match self.total_retry_budget {
+            Some(budget) => *self.retry_spent.lock().unwrap() + backoff <= budget,
+            None => true,
+        }
+    }
+
+    /// Whether the error is likely transient, which is the case for timeouts,
+    /// dropped connections and responses with a retryable status.
+    fn is_retryable(&self, e: &Report) -> bool {
+        e.chain().any(|e| {
+            if let Some(e) = e.downcast_ref::<ReqwestError>() {
+                return e.is_timeout()
+                    || e.is_connect()
+                    || matches!(e.status(), Some(status) if self.is_retryable_status(status));
+            }
+            if let Some(e) = e.downcast_ref::<io::Error>() {
+                return matches!(
+                    e.kind(),
+                    io::ErrorKind::TimedOut
+                        | io::ErrorKind::ConnectionReset
+                        | io::ErrorKind::ConnectionAborted
+                        | io::ErrorKind::UnexpectedEof
+                );
+            }
+            matches!(e.downcast_ref(), Some(Error::RequestTimedOut { .. }))
+        })
+    }
+
+    fn is_retryable_status(&self, status: StatusCode) -> bool {
+        status.is_server_error()
+            || status == StatusCode::TOO_MANY_REQUESTS
+            || self.retryable_statuses.contains(&status.as_u16())
+    }
+}
+
+/// Adds up to half of `backoff` to it, such that clients failing at the same
+/// time do not retry at the same time.
+fn jittered(backoff: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.subsec_nanos());
+    backoff.saturating_add((backoff / 2000).saturating_mul(nanos % 1000))
+}
@@ -0,0 +1,12 @@
+use std::fmt;
+
+/// A value which must not end up in logs, such as a token. It is left out
+/// of the `Debug` output of [`BinswapGithub`](crate::BinswapGithub).
+#[derive(Clone)]
+pub(crate) struct Secret(pub(crate) String);
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
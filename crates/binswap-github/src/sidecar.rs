@@ -0,0 +1,129 @@
+use binstalk::helpers::remote::{Client, Url};
+use color_eyre::Result;
+
+use crate::{AssetInfo, BinswapGithub, Error, ReleaseInfo};
+
+/// The suffixes of files holding the checksum of a single asset.
+const CHECKSUM_SUFFIXES: &[&str] = &[".sha256", ".sha256sum", ".sha512", ".sha512sum"];
+/// The names of files holding the checksums of all assets of a release.
+pub(crate) const CHECKSUM_FILES: &[&str] = &[
+    "sha256sums",
+    "sha256sums.txt",
+    "sha512sums",
+    "sha512sums.txt",
+    "checksums.txt",
+];
+/// The suffixes of files holding a signature of a single asset.
+const SIGNATURE_SUFFIXES: &[&str] = &[".sig", ".asc", ".minisig"];
+
+/// The files published next to the asset of a release to verify it, see
+/// [`fetch_verification_files`](BinswapGithub::fetch_verification_files).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct VerificationFiles {
+    /// The version of the release.
+    pub version: String,
+    /// The target the asset was matched for.
+    pub target: String,
+    /// The asset which would be downloaded.
+    pub asset: AssetInfo,
+    /// The file with the checksum of the asset, if any. This might be a file
+    /// listing the checksums of every asset of the release.
+    pub checksum: Option<SidecarFile>,
+    /// The file with a signature of the asset, if any.
+    pub signature: Option<SidecarFile>,
+}
+
+/// A downloaded file attached to a release.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SidecarFile {
+    /// The file name of the asset.
+    pub name: String,
+    /// The contents of the file.
+    pub bytes: Vec<u8>,
+}
+
+impl BinswapGithub {
+    /// Fetches the checksum and signature files of the asset which would be
+    /// downloaded, without downloading the asset itself. This leaves the
+    /// verification of the asset to the caller.
+    pub async fn fetch_verification_files(&self) -> Result<VerificationFiles> {
+        let this = &self.fresh_budget();
+        let client = this.client()?;
+        let resolved = this.resolve(&client).await?;
+        let meta = this.fetch_binstall_meta(&client, &resolved).await?;
+        let release = match resolved.release {
+            Some(release) => release,
+            None => {
+                this.fetch_release_for_version(&client, &resolved.version)
+                    .await?
+            }
+        };
+        release.ensure_has_assets()?;
+
+        let (targets, total) = this.candidate_targets().await;
+        let tried = targets.len();
+        for target in targets {
+            let target_meta = this.target_meta(&meta, &target);
+            let Some((asset, _)) =
+                this.matched_asset(&release, &resolved.version, &target, &target_meta)
+            else {
+                continue;
+            };
+
+            let checksum = find_sidecar(&release, asset, CHECKSUM_SUFFIXES).or_else(|| {
+                release
+                    .assets
+                    .iter()
+                    .find(|a| CHECKSUM_FILES.contains(&a.name.to_lowercase().as_str()))
+            });
+            let signature = find_sidecar(&release, asset, SIGNATURE_SUFFIXES);
+
+            return Ok(VerificationFiles {
+                version: resolved.version,
+                checksum: this.fetch_sidecar(&client, checksum).await?,
+                signature: this.fetch_sidecar(&client, signature).await?,
+                target,
+                asset: asset.clone(),
+            });
+        }
+
+        Err(Error::AssetNotFound {
+            version: resolved.version,
+            tried,
+            total,
+        }
+        .into())
+    }
+
+    pub(crate) async fn fetch_sidecar(
+        &self,
+        client: &Client,
+        asset: Option<&AssetInfo>,
+    ) -> Result<Option<SidecarFile>> {
+        let Some(asset) = asset else {
+            return Ok(None);
+        };
+        let url = Url::parse(&asset.browser_download_url)?;
+        let bytes = self
+            .retrying(|| async { Ok(self.get_download(client, &url).await?.bytes().await?) })
+            .await?;
+        Ok(Some(SidecarFile {
+            name: asset.name.clone(),
+            bytes: bytes.to_vec(),
+        }))
+    }
+}
+
+/// The asset of `release` named like `asset` with one of `suffixes`.
+pub(crate) fn find_sidecar<'a>(
+    release: &'a ReleaseInfo,
+    asset: &AssetInfo,
+    suffixes: &[&str],
+) -> Option<&'a AssetInfo> {
+    suffixes.iter().find_map(|suffix| {
+        let name = format!("{}{suffix}", asset.name);
+        release.assets.iter().find(|a| a.name == name)
+    })
+}
@@ -0,0 +1,105 @@
+use binstalk::helpers::remote::{Client, StatusCode};
+use color_eyre::Result;
+use serde::Deserialize;
+
+use crate::{BinswapGithub, Error};
+
+#[derive(Debug, Deserialize)]
+struct GitRef {
+    object: GitObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitObject {
+    #[serde(rename = "type")]
+    kind: String,
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitTag {
+    tagger: Option<Tagger>,
+    verification: Option<Verification>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tagger {
+    email: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Verification {
+    verified: bool,
+    reason: String,
+}
+
+impl BinswapGithub {
+    /// Checks that the tag of the release is signed and verified by GitHub,
+    /// and that it was made by one of the allowed signers if any are given.
+    ///
+    /// If `tag` is not known, the tag is looked up both with and without a
+    /// leading `v` on `version`.
+    pub(crate) async fn verify_tag(
+        &self,
+        client: &Client,
+        version: &str,
+        tag: Option<&str>,
+    ) -> Result<()> {
+        self.ensure_github("verifying signed tags")?;
+
+        let candidates = match tag {
+            Some(tag) => vec![tag.to_string()],
+            None => vec![version.to_string(), format!("v{version}")],
+        };
+
+        for tag in candidates {
+            let url = self.api_url(&format!("git/ref/tags/{tag}"))?;
+            let git_ref: Option<GitRef> = self
+                .retrying(|| async {
+                    let res = self.api_send(client, &url).await?;
+                    if res.status() == StatusCode::NOT_FOUND {
+                        return Ok(None);
+                    }
+                    Ok(Some(res.json().await?))
+                })
+                .await?;
+            let Some(git_ref) = git_ref else {
+                continue;
+            };
+
+            let not_verified = |reason: &str| Error::TagNotVerified {
+                tag: tag.clone(),
+                reason: reason.to_string(),
+            };
+
+            if git_ref.object.kind != "tag" {
+                return Err(not_verified("the tag is not an annotated tag").into());
+            }
+
+            let url = self.api_url(&format!("git/tags/{}", git_ref.object.sha))?;
+            let git_tag: GitTag = self
+                .retrying(|| async { Ok(self.api_send(client, &url).await?.json().await?) })
+                .await?;
+
+            match git_tag.verification {
+                Some(verification) if verification.verified => {}
+                Some(verification) => return Err(not_verified(&verification.reason).into()),
+                None => return Err(not_verified("unsigned").into()),
+            }
+
+            if let Some(signers) = &self.allowed_tag_signers {
+                let email = git_tag.tagger.and_then(|tagger| tagger.email);
+                if !email.iter().any(|email| signers.contains(email)) {
+                    return Err(not_verified("the tagger is not an allowed signer").into());
+                }
+            }
+
+            return Ok(());
+        }
+
+        Err(Error::ReleaseNotFound {
+            tag: tag.unwrap_or(version).to_string(),
+        }
+        .into())
+    }
+}
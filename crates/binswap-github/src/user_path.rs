@@ -0,0 +1,118 @@
+use std::path::Path;
+
+use color_eyre::Result;
+
+/// Adds `dir` to the `PATH` of the current user, unless it is already on it,
+/// and notifies running programs of the change. Returns whether `PATH` was
+/// changed.
+#[cfg(windows)]
+pub(crate) fn add_to_user_path(dir: &Path) -> Result<bool> {
+    use std::io;
+
+    use color_eyre::eyre::Context;
+    use winreg::{
+        enums::{RegType, HKEY_CURRENT_USER, KEY_READ, KEY_WRITE},
+        RegKey, RegValue,
+    };
+
+    let environment = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey_with_flags("Environment", KEY_READ | KEY_WRITE)
+        .wrap_err("failed to open the user environment in the registry")?;
+    let current = match environment.get_raw_value("Path") {
+        Ok(value) => value,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => RegValue {
+            bytes: vec![],
+            vtype: RegType::REG_EXPAND_SZ,
+        },
+        Err(e) => return Err(e).wrap_err("failed to read the user `PATH`"),
+    };
+    let path = from_wide(&current.bytes);
+
+    let dir = dir.to_string_lossy();
+    let normalize = |entry: &str| entry.trim().trim_end_matches('\\').to_lowercase();
+    if path
+        .split(';')
+        .any(|entry| normalize(entry) == normalize(&dir))
+    {
+        return Ok(false);
+    }
+
+    let new_path = if path.is_empty() || path.ends_with(';') {
+        format!("{path}{dir}")
+    } else {
+        format!("{path};{dir}")
+    };
+    // Keep `REG_EXPAND_SZ` so entries like `%USERPROFILE%\bin` keep working.
+    let vtype = match current.vtype {
+        RegType::REG_SZ => RegType::REG_SZ,
+        _ => RegType::REG_EXPAND_SZ,
+    };
+    environment
+        .set_raw_value(
+            "Path",
+            &RegValue {
+                bytes: to_wide(&new_path),
+                vtype,
+            },
+        )
+        .wrap_err("failed to write the user `PATH`")?;
+
+    broadcast_environment_change();
+
+    Ok(true)
+}
+
+/// `PATH` is only modified on Windows, where installing into a directory
+/// does not make it runnable otherwise.
+#[cfg(not(windows))]
+pub(crate) fn add_to_user_path(_dir: &Path) -> Result<bool> {
+    Ok(false)
+}
+
+/// Tells running programs, such as Explorer, to reload the environment.
+#[cfg(windows)]
+fn broadcast_environment_change() {
+    use windows_sys::Win32::{
+        Foundation::HWND,
+        UI::WindowsAndMessaging::{SendMessageTimeoutW, SMTO_ABORTIFHUNG, WM_SETTINGCHANGE},
+    };
+
+    // Not exposed by `windows-sys`, as it is a macro in the Windows headers.
+    const HWND_BROADCAST: HWND = 0xffff;
+
+    let environment: Vec<u16> = "Environment".encode_utf16().chain([0]).collect();
+    let mut result = 0;
+    // SAFETY: `environment` is a null-terminated wide string which outlives
+    // the call, and `result` is a valid pointer.
+    unsafe {
+        SendMessageTimeoutW(
+            HWND_BROADCAST,
+            WM_SETTINGCHANGE,
+            0,
+            environment.as_ptr() as isize,
+            SMTO_ABORTIFHUNG,
+            5000,
+            &mut result,
+        );
+    }
+}
+
+/// Decodes a null-terminated UTF-16 registry string.
+#[cfg(windows)]
+fn from_wide(bytes: &[u8]) -> String {
+    let wide: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|&c| c != 0)
+        .collect();
+    String::from_utf16_lossy(&wide)
+}
+
+/// Encodes a null-terminated UTF-16 registry string.
+#[cfg(windows)]
+fn to_wide(s: &str) -> Vec<u8> {
+    s.encode_utf16()
+        .chain([0])
+        .flat_map(|c| c.to_le_bytes())
+        .collect()
+}
@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use color_eyre::Result;
+use tokio::process::Command;
+
+use crate::{sha256_file, BinswapGithub, Error};
+
+/// How an installed binary compares to the binary of the release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VerifyResult {
+    /// The installed binary is identical to the one in the release.
+    Matches,
+    /// The installed binary differs from the one in the release.
+    Differs {
+        /// The version reported by the installed binary when run with
+        /// `--version`, if it could be determined.
+        installed_version_guess: Option<String>,
+    },
+    /// No asset of the release contains the binary, so it could not be
+    /// compared.
+    Unknown,
+}
+
+impl BinswapGithub {
+    /// Checks whether the binary at `path` matches the binary of the release
+    /// which would be installed, without installing anything.
+    ///
+    /// Checksums published next to the assets cover the archives rather than
+    /// the binaries in them, so the asset is downloaded and the binary in it
+    /// is compared to the installed one.
+    pub async fn verify_installed(&self, path: impl AsRef<Path>) -> Result<VerifyResult> {
+        let this = &self.fresh_budget();
+        let path = path.as_ref();
+
+        let temp = this.scratch_dir()?;
+
+        let client = this.client()?;
+        let resolved = this.resolve(&client).await?;
+
+        let res = this.fetch_bin(&client, resolved, temp.path()).await;
+        let fetched = match res {
+            Ok(fetched) => fetched,
+            Err(e)
+                if matches!(
+                    e.downcast_ref(),
+                    Some(Error::AssetNotFound { .. } | Error::ReleaseHasNoAssets { .. })
+                ) =>
+            {
+                return Ok(VerifyResult::Unknown)
+            }
+            Err(e) => return Err(e),
+        };
+
+        let bin_path = temp.path().join(&fetched.found);
+        if sha256_file(&bin_path).await? == sha256_file(path).await? {
+            return Ok(VerifyResult::Matches);
+        }
+
+        Ok(VerifyResult::Differs {
+            installed_version_guess: guess_version(path).await,
+        })
+    }
+}
+
+/// Runs the binary with `--version`, and picks the first word of the output
+/// which looks like a version number.
+pub(crate) async fn guess_version(path: &Path) -> Option<String> {
+    let output = Command::new(path).arg("--version").output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .split_whitespace()
+        .map(|word| word.trim_start_matches('v'))
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()) && word.contains('.'))
+        .map(String::from)
+}